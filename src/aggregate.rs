@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Relative accuracy of percentile estimates
+const SKETCH_ACCURACY: f64 = 0.01;
+
+/// Aggregation applied to values extracted with `--value`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AggFn {
+    #[default]
+    Sum,
+    Avg,
+    Min,
+    Max,
+    P50,
+    P95,
+    P99,
+}
+
+impl AggFn {
+    /// The quantile for percentile aggregations
+    fn quantile(self) -> Option<f64> {
+        match self {
+            AggFn::P50 => Some(0.50),
+            AggFn::P95 => Some(0.95),
+            AggFn::P99 => Some(0.99),
+            _ => None,
+        }
+    }
+
+    /// Whether aggregates of disjoint sets of events can be summed, e.g. to
+    /// stack them in a chart
+    pub fn is_additive(self) -> bool {
+        self == AggFn::Sum
+    }
+}
+
+impl fmt::Display for AggFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AggFn::Sum => "sum",
+            AggFn::Avg => "avg",
+            AggFn::Min => "min",
+            AggFn::Max => "max",
+            AggFn::P50 => "p50",
+            AggFn::P95 => "p95",
+            AggFn::P99 => "p99",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Mergeable summary of the values in one bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    /// Only kept when a percentile is requested
+    sketch: Option<QuantileSketch>,
+}
+
+impl Aggregate {
+    pub fn new(agg: AggFn) -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sketch: agg.quantile().map(|_| QuantileSketch::new()),
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if let Some(ref mut sketch) = self.sketch {
+            sketch.add(value);
+        }
+    }
+
+    pub fn merge(&mut self, other: &Aggregate) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        match (&mut self.sketch, &other.sketch) {
+            (Some(sketch), Some(other)) => sketch.merge(other),
+            (None, Some(other)) => self.sketch = Some(other.clone()),
+            _ => {}
+        }
+    }
+
+    /// The aggregated value, or None if no values were added
+    pub fn value(&self, agg: AggFn) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        match agg {
+            AggFn::Sum => Some(self.sum),
+            AggFn::Avg => Some(self.sum / self.count as f64),
+            AggFn::Min => Some(self.min),
+            AggFn::Max => Some(self.max),
+            _ => {
+                let estimate = self.sketch.as_ref()?.quantile(agg.quantile()?)?;
+                Some(estimate.clamp(self.min, self.max))
+            }
+        }
+    }
+}
+
+impl Default for Aggregate {
+    fn default() -> Self {
+        Self::new(AggFn::default())
+    }
+}
+
+/// Log-bucketed histogram (as in DDSketch) giving percentiles within
+/// `SKETCH_ACCURACY` relative error. Sketches merge by adding bin counts, so
+/// per-worker and per-bucket sketches combine exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantileSketch {
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+    zero: u64,
+    count: u64,
+}
+
+impl QuantileSketch {
+    pub fn new() -> Self {
+        Self {
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zero: 0,
+            count: 0,
+        }
+    }
+
+    fn gamma() -> f64 {
+        (1.0 + SKETCH_ACCURACY) / (1.0 - SKETCH_ACCURACY)
+    }
+
+    fn index(magnitude: f64) -> i32 {
+        (magnitude.ln() / Self::gamma().ln()).ceil() as i32
+    }
+
+    /// Representative magnitude of a bin, within the accuracy of every value in it
+    fn bin_value(index: i32) -> f64 {
+        let gamma = Self::gamma();
+        2.0 * gamma.powi(index) / (gamma + 1.0)
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        if value > f64::MIN_POSITIVE {
+            *self.positive.entry(Self::index(value)).or_insert(0) += 1;
+        } else if value < -f64::MIN_POSITIVE {
+            *self.negative.entry(Self::index(-value)).or_insert(0) += 1;
+        } else {
+            self.zero += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        for (index, count) in &other.positive {
+            *self.positive.entry(*index).or_insert(0) += count;
+        }
+        for (index, count) in &other.negative {
+            *self.negative.entry(*index).or_insert(0) += count;
+        }
+        self.zero += other.zero;
+        self.count += other.count;
+    }
+
+    /// Estimate the value at quantile `q` (0.0 to 1.0)
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).round() as u64;
+
+        // Walk the bins from the most negative value upwards
+        let mut seen = 0;
+        for (index, count) in self.negative.iter().rev() {
+            seen += count;
+            if seen > rank {
+                return Some(-Self::bin_value(*index));
+            }
+        }
+        seen += self.zero;
+        if seen > rank {
+            return Some(0.0);
+        }
+        for (index, count) in &self.positive {
+            seen += count;
+            if seen > rank {
+                return Some(Self::bin_value(*index));
+            }
+        }
+        None
+    }
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Format an aggregated value without noise: integers as integers, other
+/// values with up to three decimals
+pub fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{:.0}", value)
+    } else {
+        let formatted = format!("{:.3}", value);
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate_of(agg: AggFn, values: impl IntoIterator<Item = f64>) -> Aggregate {
+        let mut aggregate = Aggregate::new(agg);
+        for value in values {
+            aggregate.add(value);
+        }
+        aggregate
+    }
+
+    #[test]
+    fn test_basic_aggregations() {
+        let values = [4.0, 1.0, 7.0, 2.0];
+        assert_eq!(
+            aggregate_of(AggFn::Sum, values).value(AggFn::Sum),
+            Some(14.0)
+        );
+        assert_eq!(
+            aggregate_of(AggFn::Avg, values).value(AggFn::Avg),
+            Some(3.5)
+        );
+        assert_eq!(
+            aggregate_of(AggFn::Min, values).value(AggFn::Min),
+            Some(1.0)
+        );
+        assert_eq!(
+            aggregate_of(AggFn::Max, values).value(AggFn::Max),
+            Some(7.0)
+        );
+        assert_eq!(Aggregate::new(AggFn::Avg).value(AggFn::Avg), None);
+    }
+
+    #[test]
+    fn test_percentiles_within_accuracy() {
+        let aggregate = aggregate_of(AggFn::P95, (1..=1000).map(|v| v as f64));
+        let p95 = aggregate.value(AggFn::P95).unwrap();
+        assert!(
+            (p95 - 950.0).abs() / 950.0 <= SKETCH_ACCURACY,
+            "p95 = {p95}"
+        );
+
+        let aggregate = aggregate_of(AggFn::P50, (1..=1000).map(|v| v as f64));
+        let p50 = aggregate.value(AggFn::P50).unwrap();
+        assert!(
+            (p50 - 500.0).abs() / 500.0 <= SKETCH_ACCURACY,
+            "p50 = {p50}"
+        );
+
+        // Estimates never leave the observed range
+        let aggregate = aggregate_of(AggFn::P99, [42.0]);
+        assert_eq!(aggregate.value(AggFn::P99), Some(42.0));
+    }
+
+    #[test]
+    fn test_merge_matches_single_aggregate() {
+        let mut left = aggregate_of(AggFn::P99, (1..=500).map(|v| v as f64));
+        let right = aggregate_of(AggFn::P99, (501..=1000).map(|v| v as f64));
+        left.merge(&right);
+
+        let whole = aggregate_of(AggFn::P99, (1..=1000).map(|v| v as f64));
+        assert_eq!(left, whole);
+        assert_eq!(left.value(AggFn::Sum), Some(500500.0));
+    }
+
+    #[test]
+    fn test_sketch_with_zero_and_negative_values() {
+        let mut sketch = QuantileSketch::new();
+        for value in [-10.0, 0.0, 0.0, 10.0, 20.0] {
+            sketch.add(value);
+        }
+        assert!((sketch.quantile(0.0).unwrap() + 10.0).abs() < 0.2);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert!((sketch.quantile(1.0).unwrap() - 20.0).abs() < 0.4);
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(42.0), "42");
+        assert_eq!(format_value(2.5), "2.5");
+        assert_eq!(format_value(1.0 / 3.0), "0.333");
+    }
+}
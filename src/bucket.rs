@@ -1,30 +1,382 @@
-use chrono::{DateTime, Utc};
+use crate::aggregate::{AggFn, Aggregate};
+use crate::timezone::{local_to_utc, DstPolicy};
+use chrono::{
+    DateTime, Datelike, FixedOffset, LocalResult, Months, NaiveDate, NaiveDateTime, Offset,
+    TimeZone, Utc,
+};
+use chrono_tz::Tz;
 use std::collections::BTreeMap;
+use std::fmt;
 
-#[derive(Debug, Clone)]
+/// Start of a bucket and the count of every series in it, in series order
+pub type SeriesRow = (DateTime<Utc>, Vec<usize>);
+
+/// Start of a bucket and the `--value` aggregate of every series in it
+pub type AggregateRow = (DateTime<Utc>, Vec<Aggregate>);
+
+/// Start of a bucket and the aggregated value of every series, None where a
+/// series had no values
+pub type ValueRow = (DateTime<Utc>, Vec<Option<f64>>);
+
+/// Series that `rank_series` rolls keys outside the top N into
+pub const OTHER_SERIES: &str = "other";
+
+/// Upper bound on the number of buckets `--fill` will generate
+const MAX_FILLED_BUCKETS: usize = 1_000_000;
+
+/// Resolution of the histogram kept in auto mode. Every size chosen by
+/// `calculate_auto_bucket_size` is a multiple of this, so the histogram can be
+/// re-bucketed exactly once the final size is known.
+const AUTO_RESOLUTION_MICROS: i64 = 100_000;
+
+/// Calendar units whose buckets follow real calendar boundaries and so vary
+/// in length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalendarUnit {
+    /// ISO weeks starting on Monday
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl CalendarUnit {
+    fn suffix(&self) -> &'static str {
+        match self {
+            CalendarUnit::Week => "w",
+            CalendarUnit::Month => "mo",
+            CalendarUnit::Quarter => "q",
+            CalendarUnit::Year => "y",
+        }
+    }
+
+    fn months(&self) -> u32 {
+        match self {
+            CalendarUnit::Week => 0,
+            CalendarUnit::Month => 1,
+            CalendarUnit::Quarter => 3,
+            CalendarUnit::Year => 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum BucketSize {
     Seconds(f64),
+    /// `count` consecutive calendar units, e.g. `Calendar(Month, 1)` for "1mo"
+    Calendar(CalendarUnit, u32),
     Auto,
 }
 
 impl BucketSize {
+    /// Parse "auto", a number of seconds ("60", "0.5") or a compound duration
+    /// with units from milliseconds to weeks ("5m", "1h30m", "250ms", "1d")
     pub fn from_string(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
         if s.to_lowercase() == "auto" {
-            Ok(BucketSize::Auto)
-        } else {
-            let seconds: f64 = s
-                .parse()
-                .map_err(|_| anyhow::anyhow!("Invalid bucket size: must be a number or 'auto'"))?;
-            Ok(BucketSize::Seconds(seconds))
+            return Ok(BucketSize::Auto);
+        }
+        if let Some(calendar) = parse_calendar(s)? {
+            return check_size_fits(s, calendar);
+        }
+
+        let seconds = parse_duration(s).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid bucket size '{}': expected seconds, a duration like 5m, 1h30m or 250ms, or 'auto'",
+                s
+            )
+        })?;
+
+        if seconds <= 0.0 {
+            anyhow::bail!("Invalid bucket size '{}': must be greater than zero", s);
+        }
+        if seconds < 0.000_001 {
+            anyhow::bail!(
+                "Invalid bucket size '{}': must be at least 1 microsecond",
+                s
+            );
+        }
+
+        check_size_fits(s, BucketSize::Seconds(seconds))
+    }
+
+    /// Start of the bucket containing `timestamp`, aligned to wall-clock time
+    /// in `tz` (so day and calendar buckets start at local midnight). Auto
+    /// sizes must be resolved with `TimeBucket::bucket_size` first.
+    pub fn bucket_start(&self, timestamp: DateTime<Utc>, tz: &Tz) -> DateTime<Utc> {
+        if let BucketSize::Auto = self {
+            let key = timestamp
+                .timestamp_micros()
+                .div_euclid(AUTO_RESOLUTION_MICROS)
+                * AUTO_RESOLUTION_MICROS;
+            return DateTime::from_timestamp_micros(key).unwrap_or(timestamp);
+        }
+
+        let local = timestamp.with_timezone(tz);
+        let naive = local.naive_local();
+        let start = match self {
+            BucketSize::Calendar(unit, count) => calendar_start(*unit, *count, naive.date())
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            _ => {
+                let bucket_micros = self.storage_micros();
+                let micros = naive.and_utc().timestamp_micros();
+                let key = micros.div_euclid(bucket_micros) * bucket_micros;
+                DateTime::from_timestamp_micros(key)
+                    .map(|dt| dt.naive_utc())
+                    .unwrap_or(naive)
+            }
+        };
+
+        resolve_wall_clock(tz, start, local.offset().fix())
+    }
+
+    /// End (exclusive) of the bucket starting at `start`. Day and calendar
+    /// buckets end at the next local boundary, so they can be 23 or 25 hours
+    /// long across DST changes; shorter buckets have a fixed width.
+    pub fn bucket_end(&self, start: DateTime<Utc>, tz: &Tz) -> DateTime<Utc> {
+        let fixed_width = chrono::Duration::microseconds(self.storage_micros());
+        // Past the last representable time, the bucket runs to the end of time
+        let fixed_end = || {
+            start
+                .checked_add_signed(fixed_width)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC)
+        };
+        if let BucketSize::Seconds(s) = self {
+            if *s < 86400.0 || s % 86400.0 != 0.0 {
+                return fixed_end();
+            }
+        }
+        if let BucketSize::Auto = self {
+            return fixed_end();
+        }
+
+        let local = start.with_timezone(tz);
+        let naive = local.naive_local();
+        let end = match self {
+            BucketSize::Calendar(CalendarUnit::Week, count) => {
+                naive.checked_add_signed(chrono::Duration::weeks(*count as i64))
+            }
+            BucketSize::Calendar(unit, count) => {
+                naive.checked_add_months(Months::new(unit.months() * count))
+            }
+            _ => naive.checked_add_signed(fixed_width),
+        };
+
+        match end {
+            Some(end) => resolve_wall_clock(tz, end, local.offset().fix()),
+            None => DateTime::<Utc>::MAX_UTC,
+        }
+    }
+
+    /// Width in seconds; for calendar units this is the average length
+    pub fn nominal_seconds(&self) -> f64 {
+        match self {
+            BucketSize::Seconds(s) => *s,
+            BucketSize::Calendar(CalendarUnit::Week, count) => 604_800.0 * *count as f64,
+            // Average Gregorian month: 365.2425 days / 12
+            BucketSize::Calendar(unit, count) => 2_629_746.0 * (unit.months() * count) as f64,
+            BucketSize::Auto => AUTO_RESOLUTION_MICROS as f64 / 1_000_000.0,
+        }
+    }
+
+    fn storage_micros(&self) -> i64 {
+        match self {
+            BucketSize::Seconds(s) => (s * 1_000_000.0) as i64,
+            BucketSize::Calendar(..) => (self.nominal_seconds() * 1_000_000.0) as i64,
+            BucketSize::Auto => AUTO_RESOLUTION_MICROS,
+        }
+    }
+}
+
+impl fmt::Display for BucketSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BucketSize::Seconds(s) => write!(f, "{}", format_duration(*s)),
+            BucketSize::Calendar(unit, count) => write!(f, "{}{}", count, unit.suffix()),
+            BucketSize::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Reject a parsed bucket size `s` whose width in microseconds does not fit
+/// in an i64, since bucket keys and boundaries are computed in microseconds
+fn check_size_fits(s: &str, size: BucketSize) -> anyhow::Result<BucketSize> {
+    if size.nominal_seconds() * 1_000_000.0 >= i64::MAX as f64 {
+        anyhow::bail!("Invalid bucket size '{}': bucket size too large", s);
+    }
+    Ok(size)
+}
+
+/// Parse a single calendar unit such as "1w", "1mo", "2q" or "1y".
+/// Returns `Ok(None)` when the string is not a calendar size.
+fn parse_calendar(s: &str) -> anyhow::Result<Option<BucketSize>> {
+    let number_len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let unit = match s[number_len..].to_lowercase().as_str() {
+        "w" | "week" | "weeks" => CalendarUnit::Week,
+        "mo" | "month" | "months" => CalendarUnit::Month,
+        "q" | "quarter" | "quarters" => CalendarUnit::Quarter,
+        "y" | "year" | "years" => CalendarUnit::Year,
+        _ => return Ok(None),
+    };
+
+    let count: u32 = if number_len == 0 {
+        1
+    } else {
+        s[..number_len]
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid bucket size '{}'", s))?
+    };
+    if count == 0 {
+        anyhow::bail!("Invalid bucket size '{}': must be greater than zero", s);
+    }
+
+    Ok(Some(BucketSize::Calendar(unit, count)))
+}
+
+/// Convert a bucket boundary from wall-clock time to UTC. When the boundary
+/// occurs twice, the occurrence with `preferred` offset wins so the repeated
+/// hour gets its own buckets; a boundary inside a DST gap moves to the jump.
+fn resolve_wall_clock(tz: &Tz, local: NaiveDateTime, preferred: FixedOffset) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => dt.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, latest) => {
+            if latest.offset().fix() == preferred {
+                latest.with_timezone(&Utc)
+            } else {
+                earliest.with_timezone(&Utc)
+            }
+        }
+        LocalResult::None => {
+            local_to_utc(tz, local, DstPolicy::Latest).unwrap_or_else(|| local.and_utc())
+        }
+    }
+}
+
+/// First day of the calendar bucket containing `date`. Weeks are counted
+/// from Monday 1970-01-05 and months from January of year 0, so multi-unit
+/// buckets line up the same way regardless of the data.
+fn calendar_start(unit: CalendarUnit, count: u32, date: NaiveDate) -> NaiveDate {
+    let count = count as i64;
+
+    match unit {
+        CalendarUnit::Week => {
+            let epoch_monday = NaiveDate::from_ymd_opt(1970, 1, 5).unwrap();
+            let weeks = date
+                .signed_duration_since(epoch_monday)
+                .num_days()
+                .div_euclid(7);
+            epoch_monday + chrono::Duration::weeks(weeks - weeks.rem_euclid(count))
+        }
+        _ => {
+            let months_per_bucket = unit.months() as i64 * count;
+            let index = date.year() as i64 * 12 + date.month0() as i64;
+            let start = index - index.rem_euclid(months_per_bucket);
+            NaiveDate::from_ymd_opt(
+                start.div_euclid(12) as i32,
+                start.rem_euclid(12) as u32 + 1,
+                1,
+            )
+            .unwrap_or(date)
+        }
+    }
+}
+
+/// Parse a plain number of seconds or a compound duration such as "1h30m"
+pub(crate) fn parse_duration(s: &str) -> Option<f64> {
+    if let Ok(seconds) = s.parse::<f64>() {
+        return seconds.is_finite().then_some(seconds);
+    }
+
+    let (sign, mut rest) = match s.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, s),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = 0.0;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let unit_len = rest[number_len..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len() - number_len);
+        if number_len == 0 || unit_len == 0 {
+            return None;
+        }
+
+        let value: f64 = rest[..number_len].parse().ok()?;
+        let unit_seconds = match rest[number_len..number_len + unit_len]
+            .to_lowercase()
+            .as_str()
+        {
+            "ms" => 0.001,
+            "s" | "sec" => 1.0,
+            "m" | "min" => 60.0,
+            "h" | "hr" => 3600.0,
+            "d" => 86400.0,
+            "w" => 604800.0,
+            _ => return None,
+        };
+
+        total += value * unit_seconds;
+        rest = &rest[number_len + unit_len..];
+    }
+
+    Some(sign * total)
+}
+
+/// Format a bucket size in compact human units, e.g. 5400.0 -> "1h30m"
+pub fn format_duration(seconds: f64) -> String {
+    let total_ms = (seconds * 1000.0).round();
+    if (seconds * 1000.0 - total_ms).abs() > 1e-6 || total_ms < 1.0 {
+        // Below millisecond precision: fall back to fractional seconds
+        let formatted = format!("{:.6}", seconds);
+        return format!("{}s", formatted.trim_end_matches('0').trim_end_matches('.'));
+    }
+
+    const UNITS: [(&str, u64); 6] = [
+        ("w", 604_800_000),
+        ("d", 86_400_000),
+        ("h", 3_600_000),
+        ("m", 60_000),
+        ("s", 1_000),
+        ("ms", 1),
+    ];
+
+    let mut remaining = total_ms as u64;
+    let mut out = String::new();
+    for (unit, unit_ms) in UNITS {
+        if remaining >= unit_ms {
+            out.push_str(&format!("{}{}", remaining / unit_ms, unit));
+            remaining %= unit_ms;
         }
     }
+    out
 }
 
 pub struct TimeBucket {
     bucket_size: BucketSize,
-    buckets: BTreeMap<i64, usize>,
+    /// Per-series counts keyed by start time in microseconds. In auto mode
+    /// the keys are at `AUTO_RESOLUTION_MICROS` and are re-bucketed on read.
+    /// Count vectors may be shorter than `series` when later series are empty.
+    buckets: BTreeMap<i64, Vec<usize>>,
+    /// Series names in column order; empty for the single unnamed series
+    series: Vec<String>,
     first_timestamp: Option<DateTime<Utc>>,
     last_timestamp: Option<DateTime<Utc>>,
+    /// Zone whose wall clock bucket boundaries are aligned to
+    timezone: Tz,
+    /// How `--value` samples are aggregated, if they are collected at all
+    aggregation: Option<AggFn>,
+    /// Value aggregates with the same keys and layout as `buckets`
+    values: BTreeMap<i64, Vec<Aggregate>>,
+    /// Matching lines with the same keys as `buckets`. A line counted
+    /// towards several series is one line here.
+    lines: BTreeMap<i64, usize>,
 }
 
 impl TimeBucket {
@@ -34,15 +386,148 @@ impl TimeBucket {
             None => BucketSize::Seconds(60.0), // Default: 1 minute
         };
 
-        Ok(Self {
-            bucket_size: size,
+        Ok(Self::with_size(size))
+    }
+
+    pub fn with_size(bucket_size: BucketSize) -> Self {
+        Self {
+            bucket_size,
             buckets: BTreeMap::new(),
+            series: Vec::new(),
             first_timestamp: None,
             last_timestamp: None,
-        })
+            timezone: Tz::UTC,
+            aggregation: None,
+            values: BTreeMap::new(),
+            lines: BTreeMap::new(),
+        }
+    }
+
+    /// Align bucket boundaries to wall-clock time in `tz` instead of UTC
+    pub fn with_timezone(mut self, tz: Tz) -> Self {
+        self.timezone = tz;
+        self
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    /// Count several named series side by side instead of a single one
+    pub fn with_series(mut self, names: Vec<String>) -> Self {
+        self.series = names;
+        self
+    }
+
+    /// Collect values added with `add_value` and aggregate them with `agg`
+    pub fn with_aggregation(mut self, agg: AggFn) -> Self {
+        self.aggregation = Some(agg);
+        self
+    }
+
+    pub fn aggregation(&self) -> Option<AggFn> {
+        self.aggregation
+    }
+
+    /// Names of the series, empty unless multi-series counting is in use
+    pub fn series_names(&self) -> &[String] {
+        &self.series
+    }
+
+    /// Index of the series called `name`, adding it if it is new
+    pub fn series_index(&mut self, name: &str) -> usize {
+        match self.series.iter().position(|s| s == name) {
+            Some(index) => index,
+            None => {
+                self.series.push(name.to_string());
+                self.series.len() - 1
+            }
+        }
+    }
+
+    /// Create an empty bucket set with the same configuration, e.g. for a worker
+    pub fn empty_like(&self) -> Self {
+        let mut bucket = Self::with_size(self.bucket_size.clone())
+            .with_timezone(self.timezone)
+            .with_series(self.series.clone());
+        bucket.aggregation = self.aggregation;
+        bucket
+    }
+
+    /// Merge counts and time range from another bucket set with the same size
+    pub fn merge(&mut self, other: TimeBucket) {
+        if let Some((first, last)) = other.time_range() {
+            if self.first_timestamp.is_none() || Some(first) < self.first_timestamp {
+                self.first_timestamp = Some(first);
+            }
+            if self.last_timestamp.is_none() || Some(last) > self.last_timestamp {
+                self.last_timestamp = Some(last);
+            }
+        }
+
+        // Series are matched up by name, since workers can discover them in
+        // different orders
+        let indices: Vec<usize> = if other.series == self.series {
+            (0..other.series.len()).collect()
+        } else {
+            other
+                .series
+                .iter()
+                .map(|name| self.series_index(name))
+                .collect()
+        };
+
+        let index = |series: usize| indices.get(series).copied().unwrap_or(series);
+        for (key, counts) in other.buckets {
+            let row = self.buckets.entry(key).or_default();
+            for (series, count) in counts.iter().enumerate() {
+                add_cell(row, index(series), count);
+            }
+        }
+        for (key, aggregates) in other.values {
+            let row = self.values.entry(key).or_default();
+            for (series, aggregate) in aggregates.iter().enumerate() {
+                add_cell(row, index(series), aggregate);
+            }
+        }
+        for (key, lines) in other.lines {
+            *self.lines.entry(key).or_default() += lines;
+        }
     }
 
     pub fn add(&mut self, timestamp: DateTime<Utc>) {
+        self.add_to_series(timestamp, 0);
+        self.add_line(timestamp);
+    }
+
+    /// Count one matching line, however many series it was counted towards
+    pub fn add_line(&mut self, timestamp: DateTime<Utc>) {
+        let bucket_key = self
+            .bucket_size
+            .bucket_start(timestamp, &self.timezone)
+            .timestamp_micros();
+        *self.lines.entry(bucket_key).or_default() += 1;
+    }
+
+    /// Count one event for the series at `series` (see `series_index`)
+    pub fn add_to_series(&mut self, timestamp: DateTime<Utc>, series: usize) {
+        self.record(timestamp, series);
+    }
+
+    /// Count one event and add its `--value` to the series' aggregate
+    pub fn add_value(&mut self, timestamp: DateTime<Utc>, series: usize, value: f64) {
+        let key = self.record(timestamp, series);
+        if let Some(cell) = self
+            .values
+            .get_mut(&key)
+            .and_then(|row| row.get_mut(series))
+        {
+            cell.add(value);
+        }
+    }
+
+    /// Count an event, returning its bucket key
+    fn record(&mut self, timestamp: DateTime<Utc>, series: usize) -> i64 {
         // Update first/last timestamps
         if self.first_timestamp.is_none() || Some(timestamp) < self.first_timestamp {
             self.first_timestamp = Some(timestamp);
@@ -51,17 +536,30 @@ impl TimeBucket {
             self.last_timestamp = Some(timestamp);
         }
 
-        let bucket_seconds = self.get_bucket_size();
-        let timestamp_micros = timestamp.timestamp_micros();
-        let bucket_micros = (bucket_seconds * 1_000_000.0) as i64;
-        let bucket_key = (timestamp_micros / bucket_micros) * bucket_micros;
+        // Auto mode must not key by the current size, which grows with the
+        // time range; it records at a fixed fine resolution instead
+        let bucket_key = self
+            .bucket_size
+            .bucket_start(timestamp, &self.timezone)
+            .timestamp_micros();
+
+        add_cell(self.buckets.entry(bucket_key).or_default(), series, &1);
 
-        *self.buckets.entry(bucket_key).or_insert(0) += 1;
+        // Every counted bucket gets an aggregate, so value rows line up with
+        // count rows even where no value was captured
+        if let Some(agg) = self.aggregation {
+            let row = self.values.entry(bucket_key).or_default();
+            if row.len() <= series {
+                row.resize_with(series + 1, || Aggregate::new(agg));
+            }
+        }
+        bucket_key
     }
 
     fn get_bucket_size(&self) -> f64 {
         match &self.bucket_size {
             BucketSize::Seconds(s) => *s,
+            BucketSize::Calendar(..) => self.bucket_size.nominal_seconds(),
             BucketSize::Auto => {
                 // Calculate auto bucket size based on time range
                 if let (Some(first), Some(last)) = (self.first_timestamp, self.last_timestamp) {
@@ -102,24 +600,171 @@ impl TimeBucket {
         }
     }
 
+    /// Counts per bucket, all keyed with the current (final) bucket size.
+    /// In multi-series mode this is the sum over all series.
     pub fn get_buckets(&self) -> Vec<(DateTime<Utc>, usize)> {
-        self.buckets
+        sum_series(self.get_series_buckets())
+    }
+
+    /// Counts of every series per bucket, keyed with the final bucket size
+    pub fn get_series_buckets(&self) -> Vec<SeriesRow> {
+        self.rows(&self.buckets)
+    }
+
+    /// `--value` aggregates of every series per bucket, with the same rows as
+    /// `get_series_buckets`
+    pub fn get_series_aggregates(&self) -> Vec<AggregateRow> {
+        self.rows(&self.values)
+    }
+
+    /// Like `get_buckets`, but with a zero-count entry for every empty bucket
+    /// between the first and last match. `since` and `until` (exclusive)
+    /// extend the range beyond the data.
+    pub fn get_filled_buckets(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<(DateTime<Utc>, usize)>> {
+        Ok(sum_series(self.get_filled_series_buckets(since, until)?))
+    }
+
+    /// `get_series_buckets` with empty buckets filled in, as for `get_filled_buckets`
+    pub fn get_filled_series_buckets(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<SeriesRow>> {
+        self.filled_rows(self.get_series_buckets(), since, until)
+    }
+
+    /// `get_series_aggregates` with empty buckets filled in, as for `get_filled_buckets`
+    pub fn get_filled_series_aggregates(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<AggregateRow>> {
+        self.filled_rows(self.get_series_aggregates(), since, until)
+    }
+
+    /// Rows of `cells`, re-bucketed to the final size in auto mode and padded
+    /// to one cell per series
+    fn rows<T: BucketCell>(&self, cells: &BTreeMap<i64, Vec<T>>) -> Vec<(DateTime<Utc>, Vec<T>)> {
+        let width = self.series.len().max(1);
+        let rebucketed;
+        let cells = if !matches!(self.bucket_size, BucketSize::Auto) {
+            cells
+        } else {
+            let size = self.bucket_size();
+            let mut map = BTreeMap::new();
+            for (key, row) in cells {
+                let timestamp = DateTime::from_timestamp_micros(*key).unwrap_or_else(Utc::now);
+                let start = size.bucket_start(timestamp, &self.timezone);
+                let merged: &mut Vec<T> = map.entry(start.timestamp_micros()).or_default();
+                for (series, cell) in row.iter().enumerate() {
+                    add_cell(merged, series, cell);
+                }
+            }
+            rebucketed = map;
+            &rebucketed
+        };
+
+        cells
             .iter()
-            .map(|(key, count)| {
+            .map(|(key, row)| {
                 let dt = DateTime::from_timestamp_micros(*key).unwrap_or_else(Utc::now);
-                (dt, *count)
+                let mut row = row.clone();
+                row.resize(width.max(row.len()), T::default());
+                (dt, row)
             })
             .collect()
     }
 
+    /// Add an empty row for every missing bucket between the first and last
+    /// row, extended to `since` and `until`
+    fn filled_rows<T: BucketCell>(
+        &self,
+        rows: Vec<(DateTime<Utc>, Vec<T>)>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<(DateTime<Utc>, Vec<T>)>> {
+        let size = self.bucket_size();
+        let width = self.series.len().max(1);
+
+        let first = match (since, rows.first()) {
+            (Some(since), Some((first, _))) => since.min(*first),
+            (Some(since), None) => since,
+            (None, Some((first, _))) => *first,
+            (None, None) => return Ok(rows),
+        };
+        let end = match (until, rows.last()) {
+            (Some(until), Some((last, _))) => until.max(size.bucket_end(*last, &self.timezone)),
+            (Some(until), None) => until,
+            (None, Some((last, _))) => size.bucket_end(*last, &self.timezone),
+            (None, None) => return Ok(rows),
+        };
+
+        let mut filled = BTreeMap::new();
+        let mut start = size.bucket_start(first, &self.timezone);
+        while start < end {
+            if filled.len() >= MAX_FILLED_BUCKETS {
+                anyhow::bail!(
+                    "Filling empty buckets would produce more than {} buckets; use a larger --bucket",
+                    MAX_FILLED_BUCKETS
+                );
+            }
+            filled.insert(start, vec![T::default(); width]);
+            let next = size.bucket_end(start, &self.timezone);
+            start = size.bucket_start(next, &self.timezone).max(next);
+        }
+
+        for (timestamp, row) in rows {
+            filled.insert(timestamp, row);
+        }
+
+        Ok(filled.into_iter().collect())
+    }
+
+    /// Drop the buckets that end at or before `cutoff`, e.g. to keep a
+    /// sliding window of recent events
+    pub fn evict_before(&mut self, cutoff: DateTime<Utc>) {
+        let first_kept = self
+            .bucket_size
+            .bucket_start(cutoff, &self.timezone)
+            .timestamp_micros();
+        self.buckets = self.buckets.split_off(&first_kept);
+        self.values = self.values.split_off(&first_kept);
+        self.lines = self.lines.split_off(&first_kept);
+
+        if self.buckets.is_empty() {
+            self.first_timestamp = None;
+            self.last_timestamp = None;
+        } else {
+            self.first_timestamp = self.first_timestamp.max(Some(cutoff));
+        }
+    }
+
     pub fn total_matches(&self) -> usize {
-        self.buckets.values().sum()
+        self.buckets.values().flatten().sum()
+    }
+
+    /// Number of lines counted with `add_line`, each once even when it
+    /// matched several series
+    pub fn total_lines(&self) -> usize {
+        self.lines.values().sum()
     }
 
     pub fn bucket_size_seconds(&self) -> f64 {
         self.get_bucket_size()
     }
 
+    /// The effective bucket size, with auto mode resolved to a fixed size
+    pub fn bucket_size(&self) -> BucketSize {
+        match &self.bucket_size {
+            BucketSize::Auto => BucketSize::Seconds(self.get_bucket_size()),
+            size => size.clone(),
+        }
+    }
+
     pub fn time_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
         match (self.first_timestamp, self.last_timestamp) {
             (Some(first), Some(last)) => Some((first, last)),
@@ -128,6 +773,156 @@ impl TimeBucket {
     }
 }
 
+/// Order series by total count, largest first, keeping the first `top` and
+/// rolling the rest up into a single "other" series. Returns the new series
+/// names and, for each, the indices of the original series it combines; apply
+/// it to rows with `regroup_series`.
+pub fn rank_series(
+    series: &[String],
+    rows: &[SeriesRow],
+    top: Option<usize>,
+) -> (Vec<String>, Vec<Vec<usize>>) {
+    let mut totals = vec![0usize; series.len()];
+    for (_, counts) in rows {
+        for (total, count) in totals.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..series.len()).collect();
+    order.sort_by(|a, b| {
+        totals[*b]
+            .cmp(&totals[*a])
+            .then(series[*a].cmp(&series[*b]))
+    });
+
+    let keep = top.unwrap_or(order.len()).min(order.len());
+    let (kept, rest) = order.split_at(keep);
+
+    let mut names: Vec<String> = kept.iter().map(|i| series[*i].clone()).collect();
+    let mut columns: Vec<Vec<usize>> = kept.iter().map(|i| vec![*i]).collect();
+    if !rest.is_empty() {
+        match names.iter().position(|name| name == OTHER_SERIES) {
+            Some(index) => columns[index].extend(rest),
+            None => {
+                names.push(OTHER_SERIES.to_string());
+                columns.push(rest.to_vec());
+            }
+        }
+    }
+
+    (names, columns)
+}
+
+/// Rearrange the series of `rows` into the columns chosen by `rank_series`
+pub fn regroup_series<T: BucketCell>(
+    rows: &[(DateTime<Utc>, Vec<T>)],
+    columns: &[Vec<usize>],
+) -> Vec<(DateTime<Utc>, Vec<T>)> {
+    rows.iter()
+        .map(|(timestamp, row)| {
+            let regrouped = columns
+                .iter()
+                .map(|sources| {
+                    let mut cell = T::default();
+                    for source in sources {
+                        cell.combine(&row[*source]);
+                    }
+                    cell
+                })
+                .collect();
+            (*timestamp, regrouped)
+        })
+        .collect()
+}
+
+/// Final value of every aggregate in `rows`
+pub fn aggregate_values(rows: &[AggregateRow], agg: AggFn) -> Vec<ValueRow> {
+    rows.iter()
+        .map(|(timestamp, aggregates)| {
+            let values = aggregates.iter().map(|a| a.value(agg)).collect();
+            (*timestamp, values)
+        })
+        .collect()
+}
+
+/// Value of every series over all of `rows`
+pub fn aggregate_totals(rows: &[AggregateRow], agg: AggFn) -> Vec<Option<f64>> {
+    let mut totals: Vec<Aggregate> = Vec::new();
+    for (_, aggregates) in rows {
+        for (series, aggregate) in aggregates.iter().enumerate() {
+            add_cell(&mut totals, series, aggregate);
+        }
+    }
+    totals.iter().map(|total| total.value(agg)).collect()
+}
+
+/// Running totals across series, so each series is drawn on top of the
+/// previous ones
+pub fn stack_series(rows: &[SeriesRow]) -> Vec<SeriesRow> {
+    rows.iter()
+        .map(|(timestamp, counts)| {
+            let stacked = counts
+                .iter()
+                .scan(0, |total, count| {
+                    *total += count;
+                    Some(*total)
+                })
+                .collect();
+            (*timestamp, stacked)
+        })
+        .collect()
+}
+
+/// `stack_series` for additive values; series without a value add nothing
+pub fn stack_values(rows: &[ValueRow]) -> Vec<ValueRow> {
+    rows.iter()
+        .map(|(timestamp, values)| {
+            let mut total = 0.0;
+            let stacked = values
+                .iter()
+                .map(|value| {
+                    total += value.unwrap_or(0.0);
+                    value.map(|_| total)
+                })
+                .collect();
+            (*timestamp, stacked)
+        })
+        .collect()
+}
+
+/// Contents of one series in one bucket: a count or a value aggregate.
+/// Cells combine when workers are merged, auto-mode buckets are re-bucketed
+/// or series are rolled up.
+pub trait BucketCell: Clone + Default {
+    fn combine(&mut self, other: &Self);
+}
+
+impl BucketCell for usize {
+    fn combine(&mut self, other: &Self) {
+        *self += other;
+    }
+}
+
+impl BucketCell for Aggregate {
+    fn combine(&mut self, other: &Self) {
+        self.merge(other);
+    }
+}
+
+fn add_cell<T: BucketCell>(row: &mut Vec<T>, series: usize, cell: &T) {
+    if row.len() <= series {
+        row.resize(series + 1, T::default());
+    }
+    row[series].combine(cell);
+}
+
+fn sum_series(rows: Vec<SeriesRow>) -> Vec<(DateTime<Utc>, usize)> {
+    rows.into_iter()
+        .map(|(timestamp, counts)| (timestamp, counts.iter().sum()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,6 +942,473 @@ mod tests {
         assert!(BucketSize::from_string("invalid").is_err());
     }
 
+    #[test]
+    fn test_bucket_size_durations() {
+        let cases = [
+            ("5m", 300.0),
+            ("1h30m", 5400.0),
+            ("250ms", 0.25),
+            ("1d", 86400.0),
+            ("1w2d", 777_600.0),
+            ("1.5h", 5400.0),
+            ("90s", 90.0),
+            ("1m30s", 90.0),
+            ("10MIN", 600.0),
+            ("0.5", 0.5),
+        ];
+        for (input, expected) in cases {
+            match BucketSize::from_string(input).unwrap() {
+                BucketSize::Seconds(s) => assert!((s - expected).abs() < 1e-9, "{}", input),
+                _ => panic!("Expected Seconds variant for {}", input),
+            }
+        }
+
+        for invalid in ["5x", "m5", "1h 30m", "", "h", "1.2.3s", "nan", "inf"] {
+            assert!(BucketSize::from_string(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_bucket_size_rejects_zero_and_negative() {
+        for input in ["0", "0s", "-5", "-5m", "0.0000000001"] {
+            let err = BucketSize::from_string(input).unwrap_err().to_string();
+            assert!(err.contains(input), "{}", err);
+        }
+        assert!(TimeBucket::new(Some("0".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_bucket_size_rejects_too_large() {
+        for input in ["1e300", "100000000w", "400000y", "10000000000000d"] {
+            let err = BucketSize::from_string(input).unwrap_err().to_string();
+            assert!(err.contains("bucket size too large"), "{}", err);
+        }
+        assert!(BucketSize::from_string("1000y").is_ok());
+
+        // Boundaries past the last representable time are clamped to it
+        let start = Utc.with_ymd_and_hms(2025, 10, 3, 0, 0, 0).unwrap();
+        for size in ["200000y", "10000000w", "9000000000000"] {
+            let size = BucketSize::from_string(size).unwrap();
+            let near_end = DateTime::<Utc>::MAX_UTC - chrono::Duration::days(1);
+            assert!(size.bucket_end(start, &Tz::UTC) > start);
+            assert_eq!(
+                size.bucket_end(near_end, &Tz::UTC),
+                DateTime::<Utc>::MAX_UTC
+            );
+        }
+    }
+
+    #[test]
+    fn test_calendar_bucket_size_from_string() {
+        assert_eq!(
+            BucketSize::from_string("1mo").unwrap(),
+            BucketSize::Calendar(CalendarUnit::Month, 1)
+        );
+        assert_eq!(
+            BucketSize::from_string("2w").unwrap(),
+            BucketSize::Calendar(CalendarUnit::Week, 2)
+        );
+        assert_eq!(
+            BucketSize::from_string("1q").unwrap(),
+            BucketSize::Calendar(CalendarUnit::Quarter, 1)
+        );
+        assert_eq!(
+            BucketSize::from_string("year").unwrap(),
+            BucketSize::Calendar(CalendarUnit::Year, 1)
+        );
+        assert!(BucketSize::from_string("0mo").is_err());
+        assert!(BucketSize::from_string("1.5mo").is_err());
+        assert_eq!(BucketSize::from_string("3mo").unwrap().to_string(), "3mo");
+    }
+
+    #[test]
+    fn test_calendar_week_buckets_start_monday() {
+        let size = BucketSize::Calendar(CalendarUnit::Week, 1);
+        // Sunday 2025-10-05 belongs to the week starting Monday 2025-09-29
+        let sunday = Utc.with_ymd_and_hms(2025, 10, 5, 23, 59, 59).unwrap();
+        let start = size.bucket_start(sunday, &Tz::UTC);
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 9, 29, 0, 0, 0).unwrap());
+        assert_eq!(
+            size.bucket_end(start, &Tz::UTC),
+            Utc.with_ymd_and_hms(2025, 10, 6, 0, 0, 0).unwrap()
+        );
+        let monday = Utc.with_ymd_and_hms(2025, 10, 6, 0, 0, 0).unwrap();
+        assert_eq!(size.bucket_start(monday, &Tz::UTC), monday);
+    }
+
+    #[test]
+    fn test_calendar_month_quarter_year_buckets() {
+        let ts = Utc.with_ymd_and_hms(2024, 2, 29, 12, 0, 0).unwrap();
+
+        let month = BucketSize::Calendar(CalendarUnit::Month, 1);
+        let start = month.bucket_start(ts, &Tz::UTC);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            month.bucket_end(start, &Tz::UTC),
+            Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()
+        );
+
+        let quarter = BucketSize::Calendar(CalendarUnit::Quarter, 1);
+        let start = quarter.bucket_start(
+            Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap(),
+            &Tz::UTC,
+        );
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            quarter.bucket_end(start, &Tz::UTC),
+            Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap()
+        );
+
+        let year = BucketSize::Calendar(CalendarUnit::Year, 1);
+        assert_eq!(
+            year.bucket_start(ts, &Tz::UTC),
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calendar_month_bucketing() {
+        let mut bucket = TimeBucket::new(Some("1mo".to_string())).unwrap();
+        bucket.add(Utc.with_ymd_and_hms(2025, 1, 31, 23, 59, 59).unwrap());
+        bucket.add(Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap());
+        bucket.add(Utc.with_ymd_and_hms(2025, 2, 28, 12, 0, 0).unwrap());
+
+        let buckets = bucket.get_buckets();
+        assert_eq!(
+            buckets,
+            vec![
+                (Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(), 1),
+                (Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap(), 2),
+            ]
+        );
+        assert_eq!(bucket.bucket_size().to_string(), "1mo");
+    }
+
+    #[test]
+    fn test_day_buckets_align_to_local_midnight() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        let mut bucket = TimeBucket::new(Some("1d".to_string()))
+            .unwrap()
+            .with_timezone(tz);
+        // 23:30 UTC on 2025-07-01 is already 00:30 BST on 2025-07-02
+        bucket.add(Utc.with_ymd_and_hms(2025, 7, 1, 22, 30, 0).unwrap());
+        bucket.add(Utc.with_ymd_and_hms(2025, 7, 1, 23, 30, 0).unwrap());
+
+        let buckets = bucket.get_buckets();
+        assert_eq!(
+            buckets,
+            vec![
+                (Utc.with_ymd_and_hms(2025, 6, 30, 23, 0, 0).unwrap(), 1),
+                (Utc.with_ymd_and_hms(2025, 7, 1, 23, 0, 0).unwrap(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day_buckets_across_dst_changes() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        let day = BucketSize::from_string("1d").unwrap();
+
+        // Spring forward: 2025-03-30 is 23 hours long
+        let start = day.bucket_start(Utc.with_ymd_and_hms(2025, 3, 30, 12, 0, 0).unwrap(), &tz);
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 3, 30, 0, 0, 0).unwrap());
+        assert_eq!(
+            day.bucket_end(start, &tz),
+            Utc.with_ymd_and_hms(2025, 3, 30, 23, 0, 0).unwrap()
+        );
+
+        // Fall back: 2025-10-26 is 25 hours long
+        let start = day.bucket_start(Utc.with_ymd_and_hms(2025, 10, 26, 12, 0, 0).unwrap(), &tz);
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 10, 25, 23, 0, 0).unwrap());
+        assert_eq!(
+            day.bucket_end(start, &tz),
+            Utc.with_ymd_and_hms(2025, 10, 27, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hour_buckets_keep_repeated_hour_separate() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        let hour = BucketSize::from_string("1h").unwrap();
+        // 01:30 BST and 01:30 GMT on 2025-10-26 are an hour apart
+        let first = Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2025, 10, 26, 1, 30, 0).unwrap();
+        assert_eq!(
+            hour.bucket_start(first, &tz),
+            Utc.with_ymd_and_hms(2025, 10, 26, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            hour.bucket_start(second, &tz),
+            Utc.with_ymd_and_hms(2025, 10, 26, 1, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_filled_buckets_include_gaps() {
+        let mut bucket = TimeBucket::new(Some("1m".to_string())).unwrap();
+        bucket.add(Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 10).unwrap());
+        bucket.add(Utc.with_ymd_and_hms(2025, 10, 3, 12, 3, 20).unwrap());
+
+        let filled = bucket.get_filled_buckets(None, None).unwrap();
+        let counts: Vec<usize> = filled.iter().map(|(_, c)| *c).collect();
+        assert_eq!(counts, vec![1, 0, 0, 1]);
+        assert_eq!(
+            filled[1].0,
+            Utc.with_ymd_and_hms(2025, 10, 3, 12, 1, 0).unwrap()
+        );
+        // Unfilled output is unchanged
+        assert_eq!(bucket.get_buckets().len(), 2);
+    }
+
+    #[test]
+    fn test_evict_before() {
+        let mut bucket = TimeBucket::new(Some("1m".to_string()))
+            .unwrap()
+            .with_aggregation(AggFn::Sum);
+        bucket.add_value(
+            Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 10).unwrap(),
+            0,
+            1.0,
+        );
+        bucket.add_value(
+            Utc.with_ymd_and_hms(2025, 10, 3, 12, 1, 10).unwrap(),
+            0,
+            2.0,
+        );
+        bucket.add_value(
+            Utc.with_ymd_and_hms(2025, 10, 3, 12, 2, 10).unwrap(),
+            0,
+            4.0,
+        );
+
+        // The bucket holding the cutoff is kept whole
+        let cutoff = Utc.with_ymd_and_hms(2025, 10, 3, 12, 1, 30).unwrap();
+        bucket.evict_before(cutoff);
+        assert_eq!(bucket.total_matches(), 2);
+        assert_eq!(
+            aggregate_totals(&bucket.get_series_aggregates(), AggFn::Sum),
+            vec![Some(6.0)]
+        );
+        assert_eq!(bucket.time_range().unwrap().0, cutoff);
+
+        bucket.evict_before(Utc.with_ymd_and_hms(2025, 10, 3, 13, 0, 0).unwrap());
+        assert!(bucket.get_buckets().is_empty());
+        assert_eq!(bucket.time_range(), None);
+    }
+
+    #[test]
+    fn test_filled_buckets_extend_to_bounds() {
+        let mut bucket = TimeBucket::new(Some("1h".to_string())).unwrap();
+        bucket.add(Utc.with_ymd_and_hms(2025, 10, 3, 12, 30, 0).unwrap());
+
+        let filled = bucket
+            .get_filled_buckets(
+                Some(Utc.with_ymd_and_hms(2025, 10, 3, 10, 0, 0).unwrap()),
+                Some(Utc.with_ymd_and_hms(2025, 10, 3, 15, 0, 0).unwrap()),
+            )
+            .unwrap();
+        let counts: Vec<usize> = filled.iter().map(|(_, c)| *c).collect();
+        assert_eq!(counts, vec![0, 0, 1, 0, 0]);
+
+        // Bounds alone are enough to produce an all-zero series
+        let empty = TimeBucket::new(Some("1h".to_string())).unwrap();
+        let filled = empty
+            .get_filled_buckets(
+                Some(Utc.with_ymd_and_hms(2025, 10, 3, 10, 0, 0).unwrap()),
+                Some(Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap()),
+            )
+            .unwrap();
+        assert_eq!(filled.len(), 2);
+    }
+
+    #[test]
+    fn test_filled_calendar_buckets() {
+        let mut bucket = TimeBucket::new(Some("1mo".to_string())).unwrap();
+        bucket.add(Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap());
+        bucket.add(Utc.with_ymd_and_hms(2025, 4, 2, 0, 0, 0).unwrap());
+
+        let filled = bucket.get_filled_buckets(None, None).unwrap();
+        let months: Vec<u32> = filled.iter().map(|(ts, _)| ts.month()).collect();
+        assert_eq!(months, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_filled_buckets_limit() {
+        let mut bucket = TimeBucket::new(Some("1ms".to_string())).unwrap();
+        bucket.add(Utc.with_ymd_and_hms(2025, 10, 3, 0, 0, 0).unwrap());
+        bucket.add(Utc.with_ymd_and_hms(2025, 10, 4, 0, 0, 0).unwrap());
+        assert!(bucket.get_filled_buckets(None, None).is_err());
+    }
+
+    #[test]
+    fn test_series_counts() {
+        let mut bucket = TimeBucket::new(Some("1m".to_string()))
+            .unwrap()
+            .with_series(vec!["error".to_string(), "warn".to_string()]);
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        bucket.add_to_series(ts, 0);
+        bucket.add_to_series(ts, 1);
+        bucket.add_to_series(ts + chrono::Duration::minutes(2), 0);
+
+        assert_eq!(
+            bucket.get_series_buckets(),
+            vec![
+                (ts, vec![1, 1]),
+                (ts + chrono::Duration::minutes(2), vec![1, 0])
+            ]
+        );
+        assert_eq!(bucket.get_buckets()[0].1, 2);
+        assert_eq!(bucket.total_matches(), 3);
+
+        let filled = bucket.get_filled_series_buckets(None, None).unwrap();
+        assert_eq!(filled[1], (ts + chrono::Duration::minutes(1), vec![0, 0]));
+    }
+
+    #[test]
+    fn test_merge_series_by_name() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let mut left = TimeBucket::new(Some("1m".to_string())).unwrap();
+        let a = left.series_index("a");
+        left.add_to_series(ts, a);
+
+        let mut right = left.empty_like().with_series(Vec::new());
+        let b = right.series_index("b");
+        let a = right.series_index("a");
+        right.add_to_series(ts, b);
+        right.add_to_series(ts, a);
+        right.add_to_series(ts, a);
+
+        left.merge(right);
+        assert_eq!(left.series_names(), ["a", "b"]);
+        assert_eq!(left.get_series_buckets(), vec![(ts, vec![3, 1])]);
+    }
+
+    #[test]
+    fn test_total_lines_counts_each_line_once() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2025, 10, 3, 12, 5, 0).unwrap();
+        let mut bucket = TimeBucket::new(Some("1m".to_string()))
+            .unwrap()
+            .with_series(vec!["a".to_string(), "b".to_string()]);
+
+        // One line matching both series, one matching only the second
+        bucket.add_to_series(ts, 0);
+        bucket.add_to_series(ts, 1);
+        bucket.add_line(ts);
+        let mut other = bucket.empty_like();
+        other.add_to_series(later, 1);
+        other.add_line(later);
+        bucket.merge(other);
+
+        assert_eq!(bucket.total_matches(), 3);
+        assert_eq!(bucket.total_lines(), 2);
+
+        bucket.evict_before(later);
+        assert_eq!(bucket.total_lines(), 1);
+    }
+
+    #[test]
+    fn test_rank_series_top_n() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let series: Vec<String> = ["api", "db", "web", "cache"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let rows = vec![(ts, vec![1, 5, 3, 1]), (ts, vec![2, 0, 0, 0])];
+
+        let (names, columns) = rank_series(&series, &rows, Some(2));
+        let ranked = regroup_series(&rows, &columns);
+        assert_eq!(names, vec!["db", "api", "other"]);
+        assert_eq!(ranked[0].1, vec![5, 1, 4]);
+        assert_eq!(ranked[1].1, vec![0, 2, 0]);
+
+        // No rollup when nothing is dropped
+        let (names, _) = rank_series(&series, &rows, None);
+        assert_eq!(names, vec!["db", "api", "web", "cache"]);
+    }
+
+    #[test]
+    fn test_value_aggregates() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let mut bucket = TimeBucket::new(Some("1m".to_string()))
+            .unwrap()
+            .with_aggregation(AggFn::Max);
+        bucket.add_value(ts, 0, 120.0);
+        bucket.add_value(ts + chrono::Duration::seconds(5), 0, 80.0);
+        // Counted, but without a value
+        bucket.add(ts + chrono::Duration::minutes(2));
+
+        let mut other = bucket.empty_like();
+        other.add_value(ts + chrono::Duration::seconds(30), 0, 300.0);
+        bucket.merge(other);
+
+        let aggregates = bucket.get_filled_series_aggregates(None, None).unwrap();
+        assert_eq!(
+            aggregates.len(),
+            bucket.get_filled_series_buckets(None, None).unwrap().len()
+        );
+        assert_eq!(
+            aggregate_values(&aggregates, AggFn::Max)
+                .into_iter()
+                .map(|(_, values)| values[0])
+                .collect::<Vec<_>>(),
+            vec![Some(300.0), None, None]
+        );
+        assert_eq!(aggregate_totals(&aggregates, AggFn::Sum), vec![Some(500.0)]);
+        assert_eq!(bucket.total_matches(), 4);
+    }
+
+    #[test]
+    fn test_regroup_merges_aggregates() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let mut bucket = TimeBucket::new(Some("1m".to_string()))
+            .unwrap()
+            .with_aggregation(AggFn::Avg);
+        for (key, value) in [("a", 10.0), ("a", 20.0), ("b", 30.0), ("c", 60.0)] {
+            let index = bucket.series_index(key);
+            bucket.add_value(ts, index, value);
+        }
+
+        let series = bucket.series_names().to_vec();
+        let (names, columns) = rank_series(&series, &bucket.get_series_buckets(), Some(1));
+        assert_eq!(names, vec!["a", "other"]);
+        let regrouped = regroup_series(&bucket.get_series_aggregates(), &columns);
+        assert_eq!(
+            aggregate_values(&regrouped, AggFn::Avg)[0].1,
+            vec![Some(15.0), Some(45.0)]
+        );
+    }
+
+    #[test]
+    fn test_stack_values() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        assert_eq!(
+            stack_values(&[(ts, vec![Some(1.5), None, Some(2.0)])]),
+            vec![(ts, vec![Some(1.5), None, Some(3.5)])]
+        );
+    }
+
+    #[test]
+    fn test_stack_series() {
+        let ts = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        assert_eq!(
+            stack_series(&[(ts, vec![3, 1, 2])]),
+            vec![(ts, vec![3, 4, 6])]
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(60.0), "1m");
+        assert_eq!(format_duration(300.0), "5m");
+        assert_eq!(format_duration(5400.0), "1h30m");
+        assert_eq!(format_duration(0.25), "250ms");
+        assert_eq!(format_duration(1.5), "1s500ms");
+        assert_eq!(format_duration(86400.0), "1d");
+        assert_eq!(format_duration(604_800.0 + 3600.0), "1w1h");
+        assert_eq!(format_duration(0.0005), "0.0005s");
+    }
+
     #[test]
     fn test_time_bucket_creation() {
         let bucket = TimeBucket::new(None).unwrap();
@@ -226,6 +1488,46 @@ mod tests {
         assert!(size >= 60.0); // Should pick reasonable size
     }
 
+    #[test]
+    fn test_auto_buckets_use_final_size() {
+        let mut bucket = TimeBucket::new(Some("auto".to_string())).unwrap();
+
+        // Early events arrive while the range is small (60s buckets), later
+        // ones stretch it to 10 hours (1h buckets)
+        let start = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        bucket.add(start + chrono::Duration::seconds(5));
+        bucket.add(start + chrono::Duration::seconds(70));
+        bucket.add(start + chrono::Duration::minutes(45));
+        bucket.add(start + chrono::Duration::hours(10));
+
+        assert_eq!(bucket.bucket_size_seconds(), 3600.0);
+        let buckets = bucket.get_buckets();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0], (start, 3));
+        assert_eq!(buckets[1], (start + chrono::Duration::hours(10), 1));
+        for (ts, _) in &buckets {
+            assert_eq!(ts.timestamp() % 3600, 0);
+        }
+    }
+
+    #[test]
+    fn test_auto_buckets_merge_consistently() {
+        let start = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let mut a = TimeBucket::new(Some("auto".to_string())).unwrap();
+        let mut b = a.empty_like();
+
+        // Each worker alone would pick a different size
+        a.add(start);
+        a.add(start + chrono::Duration::seconds(30));
+        b.add(start + chrono::Duration::seconds(90));
+        b.add(start + chrono::Duration::hours(10));
+        a.merge(b);
+
+        let buckets = a.get_buckets();
+        assert_eq!(buckets[0], (start, 3));
+        assert_eq!(a.total_matches(), 4);
+    }
+
     #[test]
     fn test_calculate_auto_bucket_size() {
         let bucket = TimeBucket::new(Some("auto".to_string())).unwrap();
@@ -267,6 +1569,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_merge_buckets() {
+        let mut a = TimeBucket::new(Some("60".to_string())).unwrap();
+        let mut b = a.empty_like();
+
+        let ts1 = Utc.with_ymd_and_hms(2025, 10, 3, 12, 30, 10).unwrap();
+        let ts2 = Utc.with_ymd_and_hms(2025, 10, 3, 12, 30, 40).unwrap();
+        let ts3 = Utc.with_ymd_and_hms(2025, 10, 3, 12, 35, 0).unwrap();
+
+        a.add(ts2);
+        b.add(ts1);
+        b.add(ts3);
+        a.merge(b);
+
+        let buckets = a.get_buckets();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].1, 2);
+        assert_eq!(a.total_matches(), 3);
+        assert_eq!(a.time_range(), Some((ts1, ts3)));
+    }
+
     #[test]
     fn test_empty_bucket() {
         let bucket = TimeBucket::new(None).unwrap();
@@ -1,3 +1,7 @@
+use crate::aggregate::AggFn;
+use crate::structured::StructuredFormat;
+use crate::timezone::{parse_timezone, DstPolicy};
+use chrono_tz::Tz;
 use clap::Parser;
 
 #[derive(Parser, Debug, Clone)]
@@ -17,9 +21,10 @@ pub struct Args {
     pub pattern: Option<String>,
 
     /// Log files to search (supports .gz files). If no files provided, reads from stdin.
+    /// Glob patterns (`*`, `**`, `{a,b}`) and directories are expanded by logpile itself.
     #[arg(
         value_name = "FILES",
-        help = "Log files to process (supports glob patterns and .gz files). If omitted, reads from stdin."
+        help = "Log files, directories or glob patterns (e.g. 'logs/**/*.log*'). If omitted, reads from stdin."
     )]
     pub files: Vec<String>,
 
@@ -54,19 +59,104 @@ pub struct Args {
         long,
         short = 't',
         value_name = "FMT",
-        help = "Custom timestamp format (e.g., \"%Y-%m-%d %H:%M:%S\")"
+        help = "Custom timestamp format (e.g., \"%Y-%m-%d %H:%M:%S\"), or epoch_s, epoch_ms, epoch_us, epoch_ns for the first number in the line (epoch infers the unit)"
     )]
     pub time_format: Option<String>,
 
-    /// Bucket size in seconds, or "auto" for automatic selection
+    /// IANA time zone that timestamps without an offset are written in
+    #[arg(
+        long,
+        value_name = "TZ",
+        help = "Time zone of timestamps without an offset (e.g. Europe/London, default: UTC)"
+    )]
+    pub input_tz: Option<String>,
+
+    /// How to resolve naive timestamps that are ambiguous or skipped by a DST change
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value_t = DstPolicy::Earliest,
+        help = "Resolve repeated or skipped local times during DST changes"
+    )]
+    pub dst_policy: DstPolicy,
+
+    /// Date that timestamps without a year are assumed not to be later than
+    #[arg(
+        long,
+        value_name = "TIME",
+        allow_hyphen_values = true,
+        help = "Give timestamps without a year (syslog, MM-DDTHH:MM:SS) the latest year that does not put them more than a day after TIME (same forms as --since; default: the file's modification time, or now)"
+    )]
+    pub reference_date: Option<String>,
+
+    /// IANA time zone used to align buckets and print timestamps
+    #[arg(
+        long,
+        value_name = "TZ",
+        help = "Align buckets to and print timestamps in this time zone (default: UTC)"
+    )]
+    pub display_tz: Option<String>,
+
+    /// Bucket size as seconds, a duration (5m, 1h30m, 250ms, 1d), a calendar
+    /// unit (1w, 1mo, 1q, 1y), or "auto"
     #[arg(
         long,
         short = 'b',
-        value_name = "SECONDS",
-        help = "Time bucket size in seconds, or \"auto\" for automatic"
+        value_name = "DURATION",
+        help = "Time bucket size (e.g. 30, 5m, 1h30m, 250ms, 1d), calendar unit (1w, 1mo, 1q, 1y), or \"auto\""
     )]
     pub bucket: Option<String>,
 
+    /// Parse every line as a JSON object instead of plain text
+    #[arg(
+        long,
+        group = "structured",
+        help = "Parse each line as a JSON object (JSON lines); non-JSON lines are skipped"
+    )]
+    pub json_logs: bool,
+
+    /// Parse every line as logfmt key=value pairs instead of plain text
+    #[arg(
+        long,
+        group = "structured",
+        help = "Parse each line as logfmt key=value pairs; lines without any pair are skipped"
+    )]
+    pub logfmt: bool,
+
+    /// Dotted path of the field holding the timestamp in structured logs; in
+    /// plain text, the name of a `name=value` or `"name": value` field
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Field holding the timestamp, e.g. @timestamp or request.start; epoch numbers are accepted (default: @timestamp, timestamp, time or ts). Without --json-logs/--logfmt, the value of NAME=... or \"NAME\": ... in each line"
+    )]
+    pub ts_field: Option<String>,
+
+    /// Field conditions every counted record must meet
+    #[arg(
+        long = "where",
+        value_name = "COND",
+        requires = "structured",
+        help = "Only count records whose field matches, e.g. level=error or status>=500 (=, !=, <, <=, >, >=; repeatable)"
+    )]
+    pub where_fields: Vec<String>,
+
+    /// Join continuation lines (stack traces etc.) onto the preceding record
+    #[arg(
+        long,
+        help = "Treat lines that do not start a record as part of the previous one, so multiline events match and count once (a record starts with a timestamp unless --record-start is given)"
+    )]
+    pub multiline: bool,
+
+    /// Regex matching the first line of every record in multiline mode
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Regex matching the first line of each record (implies --multiline), e.g. '^\\d{4}-' or '^\\{'"
+    )]
+    pub record_start: Option<String>,
+
     /// Additional regex patterns to filter (can be used multiple times)
     #[arg(
         long,
@@ -76,6 +166,121 @@ pub struct Args {
     )]
     pub grep: Vec<String>,
 
+    /// Regexes for lines to drop even when they match (can be used multiple times)
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Skip lines matching REGEX even if they match a pattern (repeatable)"
+    )]
+    pub exclude: Vec<String>,
+
+    /// Require every pattern to match instead of any
+    #[arg(
+        long,
+        help = "Only count lines matching REGEX and every --grep pattern, instead of any of them"
+    )]
+    pub all: bool,
+
+    /// Read REGEX as a boolean expression of regexes
+    #[arg(
+        long,
+        conflicts_with_all = ["grep", "series", "all", "no_default_pattern"],
+        help = "Read REGEX as a boolean expression of regexes with and, or, not and parentheses, e.g. 'ERROR and not healthcheck or FATAL'"
+    )]
+    pub expr: bool,
+
+    /// Count every pattern as its own series instead of OR-ing them together
+    #[arg(
+        long,
+        help = "Count REGEX and each --grep pattern as a separate series (label with name=REGEX; write \\= for a literal = after a name)"
+    )]
+    pub series: bool,
+
+    /// Split counts by the value of a named capture group in the pattern, or
+    /// of a field in structured input
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with = "series",
+        help = "Count each value of the named capture group NAME separately (e.g. 'status=(?P<code>5\\d\\d)' --group-by code), or of the field NAME with --json-logs/--logfmt"
+    )]
+    pub group_by: Option<String>,
+
+    /// Keep only the N largest groups, rolling the rest into "other"
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "group_by",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Show only the N most frequent groups; the rest are counted as \"other\""
+    )]
+    pub top: Option<u32>,
+
+    /// Count the matches in every input file as a separate series
+    #[arg(
+        long,
+        conflicts_with_all = ["series", "group_by"],
+        help = "Count each file as a separate series, e.g. when following several files"
+    )]
+    pub per_file: bool,
+
+    /// Extract a number from every matching line and aggregate it per bucket
+    #[arg(
+        long,
+        value_name = "REGEX",
+        help = "Aggregate a number captured by REGEX (group \"v\", else the first group, else the whole match) instead of only counting lines"
+    )]
+    pub value: Option<String>,
+
+    /// How --value numbers are combined within a bucket
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FN",
+        requires = "value",
+        help = "Aggregation for --value: sum, avg, min, max, p50, p95 or p99 [default: sum]"
+    )]
+    pub agg: Option<AggFn>,
+
+    /// Ignore events before this time (RFC 3339, date, -2h, yesterday, today 09:00)
+    #[arg(
+        long,
+        value_name = "TIME",
+        allow_hyphen_values = true,
+        help = "Only count events at or after TIME (e.g. 2025-10-03T12:00:00Z, 2025-10-03, -2h, yesterday, \"today 09:00\"); times without an offset are in --input-tz"
+    )]
+    pub since: Option<String>,
+
+    /// Ignore events at or after this time
+    #[arg(
+        long,
+        value_name = "TIME",
+        allow_hyphen_values = true,
+        help = "Only count events before TIME (same forms as --since)"
+    )]
+    pub until: Option<String>,
+
+    /// Emit zero-count rows for empty buckets (default for plots)
+    #[arg(
+        long,
+        conflicts_with = "no_fill",
+        help = "Include empty buckets with a count of zero (default for plots)"
+    )]
+    pub fill: bool,
+
+    /// Only emit buckets that received matches, even in plots
+    #[arg(long, help = "Omit empty buckets, including in plots")]
+    pub no_fill: bool,
+
+    /// Number of worker threads for batch processing (defaults to available cores)
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of parallel jobs for batch processing (default: number of CPUs)"
+    )]
+    pub jobs: Option<u32>,
+
     /// Run without a required positional regex (count all lines)
     #[arg(
         long,
@@ -89,10 +294,36 @@ pub struct Args {
     #[arg(
         long,
         short = 'f',
-        help = "Follow log file and update display in real-time"
+        help = "Follow log files (and new files matching globs) and update display in real-time"
     )]
     pub follow: bool,
 
+    /// Only keep the most recent stretch of time in follow mode
+    #[arg(
+        long,
+        value_name = "DURATION",
+        requires = "follow",
+        help = "In follow mode, only show the last DURATION (e.g. 15m), dropping older buckets"
+    )]
+    pub window: Option<String>,
+
+    /// Append one CSV row or NDJSON object per closed bucket in follow mode
+    #[arg(
+        long,
+        requires = "follow",
+        conflicts_with_all = ["value", "top"],
+        help = "In follow mode, append one CSV row or JSON line per bucket as it closes instead of redrawing (needs --csv or --json)"
+    )]
+    pub stream: bool,
+
+    /// Also emit updates for the bucket that is still open
+    #[arg(
+        long,
+        requires = "stream",
+        help = "With --stream, also emit provisional records for the still-open bucket"
+    )]
+    pub provisional: bool,
+
     /// Enable verbose output (show warnings and debug info)
     #[arg(long, short = 'v', help = "Enable verbose output with warnings")]
     pub verbose: bool,
@@ -130,10 +361,38 @@ impl Args {
         }
     }
 
+    /// Zone naive timestamps are interpreted in
+    pub fn input_tz(&self) -> anyhow::Result<Tz> {
+        self.input_tz.as_deref().map_or(Ok(Tz::UTC), parse_timezone)
+    }
+
+    /// Zone buckets are aligned to and displayed in
+    pub fn display_tz(&self) -> anyhow::Result<Tz> {
+        self.display_tz
+            .as_deref()
+            .map_or(Ok(Tz::UTC), parse_timezone)
+    }
+
     pub fn validate(&self) -> anyhow::Result<()> {
         if self.pattern.is_none() && !self.no_default_pattern {
             anyhow::bail!("REGEX pattern is required unless --no-default-pattern is set");
         }
+        if self.stream {
+            match self.output_format() {
+                OutputFormat::Csv if self.group_by.is_some() || self.per_file => anyhow::bail!(
+                    "--stream with --csv needs fixed columns; use --json for --group-by or --per-file"
+                ),
+                OutputFormat::Csv | OutputFormat::Json => {}
+                _ => anyhow::bail!("--stream needs --csv or --json"),
+            }
+            if self
+                .bucket
+                .as_deref()
+                .is_some_and(|b| b.trim().eq_ignore_ascii_case("auto"))
+            {
+                anyhow::bail!("--stream needs a fixed --bucket size, not auto");
+            }
+        }
         Ok(())
     }
 
@@ -146,6 +405,65 @@ impl Args {
         }
     }
 
+    /// Patterns to match as (series name, regex) pairs: REGEX first, then every
+    /// --grep. In multi-series mode a `name=` prefix labels the series;
+    /// otherwise, and without a label, the regex itself is the name. A
+    /// pattern such as `status\=5\d\d` escapes its `=` to match it as written.
+    pub fn labelled_patterns(&self) -> Vec<(String, String)> {
+        self.get_pattern()
+            .into_iter()
+            .chain(self.grep.iter().map(String::as_str))
+            .map(|pattern| match pattern.split_once('=') {
+                Some((name, regex)) if self.series && is_series_label(name) => {
+                    (name.to_string(), regex.to_string())
+                }
+                _ => (pattern.to_string(), pattern.to_string()),
+            })
+            .collect()
+    }
+
+    /// Whether empty buckets should be emitted with a zero count
+    pub fn fill(&self) -> bool {
+        if self.fill || self.no_fill {
+            return self.fill;
+        }
+        matches!(
+            self.output_format(),
+            OutputFormat::AsciiPlot | OutputFormat::Png
+        )
+    }
+
+    /// Whether continuation lines are joined into multiline records
+    pub fn multiline(&self) -> bool {
+        self.multiline || self.record_start.is_some()
+    }
+
+    /// Layout of structured input lines, if any
+    pub fn structured_format(&self) -> Option<StructuredFormat> {
+        if self.json_logs {
+            Some(StructuredFormat::Json)
+        } else if self.logfmt {
+            Some(StructuredFormat::Logfmt)
+        } else {
+            None
+        }
+    }
+
+    /// Aggregation of `--value` numbers, if values are extracted at all
+    pub fn aggregation(&self) -> Option<AggFn> {
+        self.value.as_ref().map(|_| self.agg.unwrap_or_default())
+    }
+
+    /// Number of worker threads to use in batch mode
+    pub fn jobs(&self) -> usize {
+        match self.jobs {
+            Some(n) => n as usize,
+            None => std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+        }
+    }
+
     /// Get the list of files, including pattern as first file if --no-default-pattern was used
     pub fn get_files(&self) -> Vec<String> {
         if self.no_default_pattern && self.pattern.is_some() {
@@ -159,6 +477,17 @@ impl Args {
     }
 }
 
+/// Series labels are identifier-like so that regexes containing `=`, such
+/// as `a|b=c` or an escaped `status\=5`, are not mistaken for `name=regex`
+fn is_series_label(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +507,33 @@ mod tests {
             png: None,
             follow: false,
             grep: vec![],
+            exclude: vec![],
+            all: false,
+            expr: false,
+            input_tz: None,
+            dst_policy: DstPolicy::Earliest,
+            reference_date: None,
+            display_tz: None,
+            since: None,
+            until: None,
+            series: false,
+            group_by: None,
+            top: None,
+            value: None,
+            agg: None,
+            json_logs: false,
+            logfmt: false,
+            ts_field: None,
+            where_fields: Vec::new(),
+            multiline: false,
+            record_start: None,
+            per_file: false,
+            window: None,
+            stream: false,
+            provisional: false,
+            fill: false,
+            no_fill: false,
+            jobs: None,
             no_default_pattern: false,
             verbose: false,
             fail_quick: false,
@@ -225,6 +581,33 @@ mod tests {
             png: None,
             follow: false,
             grep: vec![],
+            exclude: vec![],
+            all: false,
+            expr: false,
+            input_tz: None,
+            dst_policy: DstPolicy::Earliest,
+            reference_date: None,
+            display_tz: None,
+            since: None,
+            until: None,
+            series: false,
+            group_by: None,
+            top: None,
+            value: None,
+            agg: None,
+            json_logs: false,
+            logfmt: false,
+            ts_field: None,
+            where_fields: Vec::new(),
+            multiline: false,
+            record_start: None,
+            per_file: false,
+            window: None,
+            stream: false,
+            provisional: false,
+            fill: false,
+            no_fill: false,
+            jobs: None,
             no_default_pattern: false,
             verbose: false,
             fail_quick: false,
@@ -259,6 +642,33 @@ mod tests {
             png: None,
             follow: false,
             grep: vec![],
+            exclude: vec![],
+            all: false,
+            expr: false,
+            input_tz: None,
+            dst_policy: DstPolicy::Earliest,
+            reference_date: None,
+            display_tz: None,
+            since: None,
+            until: None,
+            series: false,
+            group_by: None,
+            top: None,
+            value: None,
+            agg: None,
+            json_logs: false,
+            logfmt: false,
+            ts_field: None,
+            where_fields: Vec::new(),
+            multiline: false,
+            record_start: None,
+            per_file: false,
+            window: None,
+            stream: false,
+            provisional: false,
+            fill: false,
+            no_fill: false,
+            jobs: None,
             no_default_pattern: false,
             verbose: false,
             fail_quick: false,
@@ -287,6 +697,33 @@ mod tests {
             png: None,
             follow: false,
             grep: vec![],
+            exclude: vec![],
+            all: false,
+            expr: false,
+            input_tz: None,
+            dst_policy: DstPolicy::Earliest,
+            reference_date: None,
+            display_tz: None,
+            since: None,
+            until: None,
+            series: false,
+            group_by: None,
+            top: None,
+            value: None,
+            agg: None,
+            json_logs: false,
+            logfmt: false,
+            ts_field: None,
+            where_fields: Vec::new(),
+            multiline: false,
+            record_start: None,
+            per_file: false,
+            window: None,
+            stream: false,
+            provisional: false,
+            fill: false,
+            no_fill: false,
+            jobs: None,
             no_default_pattern: false,
             verbose: false,
             fail_quick: false,
@@ -313,6 +750,33 @@ mod tests {
             png: None,
             follow: false,
             grep: vec![],
+            exclude: vec![],
+            all: false,
+            expr: false,
+            input_tz: None,
+            dst_policy: DstPolicy::Earliest,
+            reference_date: None,
+            display_tz: None,
+            since: None,
+            until: None,
+            series: false,
+            group_by: None,
+            top: None,
+            value: None,
+            agg: None,
+            json_logs: false,
+            logfmt: false,
+            ts_field: None,
+            where_fields: Vec::new(),
+            multiline: false,
+            record_start: None,
+            per_file: false,
+            window: None,
+            stream: false,
+            provisional: false,
+            fill: false,
+            no_fill: false,
+            jobs: None,
             no_default_pattern: true,
             verbose: false,
             fail_quick: false,
@@ -339,6 +803,33 @@ mod tests {
             png: None,
             follow: false,
             grep: vec![],
+            exclude: vec![],
+            all: false,
+            expr: false,
+            input_tz: None,
+            dst_policy: DstPolicy::Earliest,
+            reference_date: None,
+            display_tz: None,
+            since: None,
+            until: None,
+            series: false,
+            group_by: None,
+            top: None,
+            value: None,
+            agg: None,
+            json_logs: false,
+            logfmt: false,
+            ts_field: None,
+            where_fields: Vec::new(),
+            multiline: false,
+            record_start: None,
+            per_file: false,
+            window: None,
+            stream: false,
+            provisional: false,
+            fill: false,
+            no_fill: false,
+            jobs: None,
             no_default_pattern: false,
             verbose: false,
             fail_quick: false,
@@ -346,4 +837,88 @@ mod tests {
 
         assert_eq!(args.get_files().len(), 0);
     }
+
+    #[test]
+    fn test_fill_defaults_on_for_plots() {
+        let args = Args::try_parse_from(["logpile", "ERROR", "app.log"]).unwrap();
+        assert!(!args.fill());
+        let args = Args::try_parse_from(["logpile", "ERROR", "app.log", "--fill"]).unwrap();
+        assert!(args.fill());
+        let args = Args::try_parse_from(["logpile", "ERROR", "app.log", "-p"]).unwrap();
+        assert!(args.fill());
+        let args =
+            Args::try_parse_from(["logpile", "ERROR", "app.log", "-p", "--no-fill"]).unwrap();
+        assert!(!args.fill());
+        assert!(Args::try_parse_from(["logpile", "ERROR", "--fill", "--no-fill"]).is_err());
+    }
+
+    #[test]
+    fn test_relative_since_is_not_a_flag() {
+        let args = Args::try_parse_from(["logpile", "ERROR", "--since", "-2h", "app.log"]).unwrap();
+        assert_eq!(args.since.as_deref(), Some("-2h"));
+        assert_eq!(args.files, vec!["app.log"]);
+    }
+
+    #[test]
+    fn test_validate_stream() {
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["logpile", "ERROR", "-f", "--stream"];
+            argv.extend(extra);
+            Args::try_parse_from(argv).unwrap().validate()
+        };
+        assert!(parse(&["-c"]).is_ok());
+        assert!(parse(&["-j", "--group-by", "code"]).is_ok());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["-c", "--per-file"]).is_err());
+        assert!(parse(&["-j", "-b", "auto"]).is_err());
+        assert!(Args::try_parse_from(["logpile", "ERROR", "--stream", "-j"]).is_err());
+    }
+
+    #[test]
+    fn test_labelled_patterns() {
+        let args = Args::try_parse_from([
+            "logpile",
+            "errors=ERROR",
+            "-g",
+            "WARN",
+            "-g",
+            "slow=took [0-9]{4,}ms",
+            "-g",
+            "a|b=c",
+            "-g",
+            r"status\=5\d\d",
+            "--series",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.labelled_patterns(),
+            vec![
+                ("errors".to_string(), "ERROR".to_string()),
+                ("WARN".to_string(), "WARN".to_string()),
+                ("slow".to_string(), "took [0-9]{4,}ms".to_string()),
+                ("a|b=c".to_string(), "a|b=c".to_string()),
+                (r"status\=5\d\d".to_string(), r"status\=5\d\d".to_string()),
+            ]
+        );
+
+        // Labels are only recognised in multi-series mode
+        let args = Args::try_parse_from(["logpile", "level=error"]).unwrap();
+        assert_eq!(args.labelled_patterns()[0].1, "level=error");
+    }
+
+    #[test]
+    fn test_aggregation_defaults_to_sum() {
+        let args = Args::try_parse_from(["logpile", "GET", "--value", r"(\d+)ms"]).unwrap();
+        assert_eq!(args.aggregation(), Some(AggFn::Sum));
+        let args = Args::try_parse_from(["logpile", "GET", "--value", r"(\d+)ms", "--agg", "p95"])
+            .unwrap();
+        assert_eq!(args.aggregation(), Some(AggFn::P95));
+        assert_eq!(
+            Args::try_parse_from(["logpile", "GET"])
+                .unwrap()
+                .aggregation(),
+            None
+        );
+        assert!(Args::try_parse_from(["logpile", "GET", "--agg", "avg"]).is_err());
+    }
 }
@@ -0,0 +1,344 @@
+use anyhow::Result;
+use regex::{RegexSet, SetMatches};
+
+/// Boolean combination of patterns, each referred to by its index
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Pattern(usize),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    /// Whether the expression holds, given which patterns match. Patterns
+    /// are only tested until the result is known.
+    pub fn eval(&self, matches: &impl Fn(usize) -> bool) -> bool {
+        match self {
+            Expr::Pattern(index) => matches(*index),
+            Expr::Not(expr) => !expr.eval(matches),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.eval(matches)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.eval(matches)),
+        }
+    }
+
+    /// Parse an `--expr` expression such as `ERROR and not healthcheck or FATAL`.
+    /// `not` binds tighter than `and`, which binds tighter than `or`, and
+    /// parentheses group. Terms are regexes; quote them ('...' or "...") when
+    /// they contain spaces or parentheses or are one of the keywords. Returns
+    /// the expression and the regex of each term, in order.
+    pub fn parse(input: &str) -> Result<(Expr, Vec<String>)> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            terms: Vec::new(),
+        };
+        let expr = parser.or_expr()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            anyhow::bail!(
+                "Invalid expression '{}': unexpected {}; join terms with 'and' or 'or'",
+                input,
+                token
+            );
+        }
+        Ok((expr, parser.terms))
+    }
+}
+
+/// Decides whether a line matches: the patterns combined by an `Expr`, then
+/// the `--exclude` regexes. All of them are compiled once into a `RegexSet`,
+/// so a line is scanned in a single pass however many patterns there are.
+pub struct LineFilter {
+    /// The patterns, followed by the exclusions
+    set: RegexSet,
+    patterns: usize,
+    /// None when every line matches
+    expr: Option<Expr>,
+    /// Whether the line only needs to match some pattern, which the set
+    /// can answer without finding every match
+    any: bool,
+}
+
+impl LineFilter {
+    /// A filter matching lines for which `expr` holds over `patterns` (any
+    /// line if it is None) and that match none of `exclude`
+    pub fn new(patterns: &[&str], expr: Option<Expr>, exclude: &[&str]) -> Result<Self> {
+        let set = RegexSet::new(patterns.iter().chain(exclude))?;
+        let all_patterns = Expr::Or((0..patterns.len()).map(Expr::Pattern).collect());
+        let any = exclude.is_empty() && expr.as_ref() == Some(&all_patterns);
+
+        let mut expr = expr;
+        if !exclude.is_empty() {
+            let excluded = Expr::Or((patterns.len()..set.len()).map(Expr::Pattern).collect());
+            let kept = Expr::Not(Box::new(excluded));
+            expr = Some(match expr {
+                Some(expr) => Expr::And(vec![expr, kept]),
+                None => kept,
+            });
+        }
+        Ok(Self {
+            set,
+            patterns: patterns.len(),
+            expr,
+            any,
+        })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match self.expr {
+            None => true,
+            Some(_) if self.any => self.set.is_match(line),
+            Some(_) => self.matches(line).is_some(),
+        }
+    }
+
+    /// The patterns a line matched, if it passes the filter
+    pub fn matches(&self, line: &str) -> Option<PatternMatches> {
+        let set = self.set.matches(line);
+        let passes = self
+            .expr
+            .as_ref()
+            .is_none_or(|expr| expr.eval(&|index| set.matched(index)));
+        passes.then_some(PatternMatches {
+            set,
+            patterns: self.patterns,
+        })
+    }
+}
+
+/// Which patterns (not exclusions) matched a line, by index
+pub struct PatternMatches {
+    set: SetMatches,
+    patterns: usize,
+}
+
+impl PatternMatches {
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.set.iter().take_while(|&index| index < self.patterns)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::And => write!(f, "'and'"),
+            Token::Or => write!(f, "'or'"),
+            Token::Not => write!(f, "'not'"),
+            Token::Term(term) => write!(f, "'{}'", term),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' | '\'' => {
+                chars.next();
+                // A backslash only escapes the quote, so regex escapes
+                // such as \d stay as written
+                let mut term = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') if chars.peek() == Some(&c) => {
+                            chars.next();
+                            term.push(c);
+                        }
+                        Some(next) if next == c => break,
+                        Some(next) => term.push(next),
+                        None => anyhow::bail!("Invalid expression '{}': unclosed {}", input, c),
+                    }
+                }
+                tokens.push(Token::Term(term));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Term(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    terms: Vec<String>,
+}
+
+impl Parser {
+    fn next_is(&self, token: &Token) -> bool {
+        self.tokens.get(self.position) == Some(token)
+    }
+
+    fn or_expr(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.and_expr()?];
+        while self.next_is(&Token::Or) {
+            self.position += 1;
+            exprs.push(self.and_expr()?);
+        }
+        Ok(combine(exprs, Expr::Or))
+    }
+
+    fn and_expr(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.unary()?];
+        while self.next_is(&Token::And) {
+            self.position += 1;
+            exprs.push(self.unary()?);
+        }
+        Ok(combine(exprs, Expr::And))
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        match token {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or_expr()?;
+                if !self.next_is(&Token::Close) {
+                    anyhow::bail!("Invalid expression: missing ')'");
+                }
+                self.position += 1;
+                Ok(expr)
+            }
+            Some(Token::Term(term)) => {
+                self.terms.push(term.clone());
+                Ok(Expr::Pattern(self.terms.len() - 1))
+            }
+            Some(token) => anyhow::bail!("Invalid expression: expected a pattern, found {}", token),
+            None => anyhow::bail!("Invalid expression: expected a pattern at the end"),
+        }
+    }
+}
+
+/// A single expression as is, several joined by `join`
+fn combine(mut exprs: Vec<Expr>, join: fn(Vec<Expr>) -> Expr) -> Expr {
+    if exprs.len() == 1 {
+        exprs.remove(0)
+    } else {
+        join(exprs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(expr: &str) -> LineFilter {
+        let (expr, terms) = Expr::parse(expr).unwrap();
+        let terms: Vec<&str> = terms.iter().map(String::as_str).collect();
+        LineFilter::new(&terms, Some(expr), &[]).unwrap()
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let (expr, terms) = Expr::parse("ERROR and not healthcheck or FATAL").unwrap();
+        assert_eq!(terms, ["ERROR", "healthcheck", "FATAL"]);
+        assert_eq!(
+            expr,
+            Expr::Or(vec![
+                Expr::And(vec![
+                    Expr::Pattern(0),
+                    Expr::Not(Box::new(Expr::Pattern(1))),
+                ]),
+                Expr::Pattern(2),
+            ])
+        );
+
+        let (expr, _) = Expr::parse("ERROR and (timeout or 'connection reset')").unwrap();
+        assert_eq!(
+            expr,
+            Expr::And(vec![
+                Expr::Pattern(0),
+                Expr::Or(vec![Expr::Pattern(1), Expr::Pattern(2)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_quoted_terms() {
+        let (_, terms) = Expr::parse(r#""status=5\d\d" or 'it\'s (down)' or "and""#).unwrap();
+        assert_eq!(terms, [r"status=5\d\d", "it's (down)", "and"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("ERROR and").is_err());
+        assert!(Expr::parse("ERROR WARN").is_err());
+        assert!(Expr::parse("(ERROR or WARN").is_err());
+        assert!(Expr::parse("ERROR)").is_err());
+        assert!(Expr::parse("'ERROR").is_err());
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let f = filter("ERROR and not healthcheck or FATAL");
+        assert!(f.is_match("ERROR disk full"));
+        assert!(!f.is_match("ERROR healthcheck failed"));
+        assert!(f.is_match("FATAL healthcheck failed"));
+        assert!(!f.is_match("INFO ok"));
+    }
+
+    #[test]
+    fn test_filter_exclude() {
+        let any = Expr::Or(vec![Expr::Pattern(0), Expr::Pattern(1)]);
+        let f = LineFilter::new(&["ERROR", "WARN"], Some(any), &["healthcheck"]).unwrap();
+        assert!(f.is_match("WARN slow"));
+        assert!(!f.is_match("ERROR healthcheck failed"));
+
+        // Without patterns, everything but the exclusions matches
+        let f = LineFilter::new(&[], None, &["healthcheck"]).unwrap();
+        assert!(f.is_match("INFO ok"));
+        assert!(!f.is_match("INFO healthcheck ok"));
+    }
+
+    #[test]
+    fn test_filter_reports_matching_patterns() {
+        let patterns = ["ERROR", "WARN", "disk", "timeout"];
+        let any = Expr::Or((0..patterns.len()).map(Expr::Pattern).collect());
+        let f = LineFilter::new(&patterns, Some(any), &["slow"]).unwrap();
+
+        let matched = f.matches("ERROR disk timeout").unwrap();
+        assert_eq!(matched.iter().collect::<Vec<_>>(), [0, 2, 3]);
+        assert!(f.matches("INFO ok").is_none());
+        // The exclusion matched, and is never reported as a pattern
+        assert!(f.matches("WARN disk slow").is_none());
+
+        let f = LineFilter::new(&patterns, None, &[]).unwrap();
+        assert_eq!(f.matches("WARN").unwrap().iter().collect::<Vec<_>>(), [1]);
+    }
+}
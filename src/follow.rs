@@ -0,0 +1,456 @@
+use crate::reader::{Compression, LogReader};
+use anyhow::Result;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, ErrorKind, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often files are checked when no change notifications are available
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest wait for a change notification before files are checked anyway,
+/// in case events are lost (e.g. on network filesystems)
+const WATCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bytes read from a followed file at a time, so catching up on a large
+/// existing file never holds more than this much of it in memory
+const MAX_READ_BYTES: u64 = 1 << 20;
+
+/// Why a followed file started over from its beginning
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reopen {
+    /// The path now names a different file, e.g. after logrotate renamed the
+    /// old one and created a fresh one
+    Rotated,
+    /// The file shrank below the offset already read, e.g. after copytruncate
+    Truncated,
+}
+
+/// The result of reading a followed file
+#[derive(Debug, Default, PartialEq)]
+pub struct TailRead {
+    pub lines: Vec<String>,
+    pub reopened: Option<Reopen>,
+}
+
+/// Lines appended to a file since it was last read, with `tail -F`
+/// semantics. The byte offset of the last complete line is kept, so each
+/// read costs only the new data. The file stays open, so when it is rotated
+/// the rest of it can still be read before moving on to the new file.
+pub struct FileTail {
+    path: PathBuf,
+    /// The file being read, None while the path does not exist
+    file: Option<File>,
+    /// Bytes consumed so far, including any held in `partial`
+    offset: u64,
+    /// Bytes after the last newline, held back until the line is complete
+    partial: Vec<u8>,
+}
+
+impl FileTail {
+    /// Start at the beginning of an uncompressed file
+    pub fn open(path: &str) -> Result<Self> {
+        if is_compressed(path)? {
+            anyhow::bail!("Cannot follow compressed file: {}", path);
+        }
+        Ok(Self {
+            path: PathBuf::from(path),
+            file: Some(File::open(path)?),
+            offset: 0,
+            partial: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Read the complete lines written since the last call, up to about
+    /// `MAX_READ_BYTES` of them; call again until no lines come back to catch
+    /// up on a large file. A trailing line without a newline is held back
+    /// until the rest of it arrives, unless the file has been rotated away.
+    pub fn read_lines(&mut self) -> Result<TailRead> {
+        let mut read = TailRead::default();
+        if self.file.is_none() {
+            self.file = open_existing(&self.path)?;
+        }
+
+        if let Some(file) = &self.file {
+            if file.metadata()?.len() < self.offset {
+                self.offset = 0;
+                self.partial.clear();
+                read.reopened = Some(Reopen::Truncated);
+            }
+        }
+        if !self.read_available(&mut read.lines)? {
+            return Ok(read);
+        }
+
+        if self.is_replaced()? {
+            // Whatever was left in the rotated file has just been read
+            if !self.partial.is_empty() {
+                read.lines.push(take_line(&mut self.partial));
+            }
+            self.file = open_existing(&self.path)?;
+            self.offset = 0;
+            read.reopened = Some(Reopen::Rotated);
+            self.read_available(&mut read.lines)?;
+        }
+        Ok(read)
+    }
+
+    /// Read lines until the end of the file or `MAX_READ_BYTES`; true if the
+    /// end was reached
+    fn read_available(&mut self, lines: &mut Vec<String>) -> Result<bool> {
+        let Some(file) = &self.file else {
+            return Ok(true);
+        };
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(self.offset))?;
+
+        let mut consumed = 0;
+        while consumed < MAX_READ_BYTES {
+            let read = reader.read_until(b'\n', &mut self.partial)?;
+            if read == 0 {
+                return Ok(true);
+            }
+            consumed += read as u64;
+            self.offset += read as u64;
+            if self.partial.ends_with(b"\n") {
+                lines.push(take_line(&mut self.partial));
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether the path now names a different file than the one open. A
+    /// missing path is not a replacement yet: the old file is kept until a
+    /// new one appears.
+    fn is_replaced(&self) -> Result<bool> {
+        let Some(file) = &self.file else {
+            return Ok(false);
+        };
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => Ok(file_id(&metadata) != file_id(&file.metadata()?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Whether a file is compressed, and so cannot be followed
+pub fn is_compressed(path: &str) -> Result<bool> {
+    Ok(LogReader::new(Some(path))?.compression() != Compression::None)
+}
+
+fn open_existing(path: &Path) -> Result<Option<File>> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Device and inode, which identify a file across renames
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Without inode numbers, rotation cannot be told from appends
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Convert a buffered line to text without its line ending
+fn take_line(buffer: &mut Vec<u8>) -> String {
+    let mut end = buffer.len();
+    if buffer[..end].ends_with(b"\n") {
+        end -= 1;
+    }
+    if buffer[..end].ends_with(b"\r") {
+        end -= 1;
+    }
+    let line = String::from_utf8_lossy(&buffer[..end]).into_owned();
+    buffer.clear();
+    line
+}
+
+/// Wakes the follow loop when a followed file changes, using the platform's
+/// file notifications (inotify on Linux) or a fixed poll interval when they
+/// are unavailable
+pub struct ChangeWatcher {
+    watcher: Option<RecommendedWatcher>,
+    events: Option<Receiver<()>>,
+    /// Absolute paths of the followed files, shared with the event handler
+    names: Arc<Mutex<Vec<PathBuf>>>,
+    directories: Vec<PathBuf>,
+}
+
+impl ChangeWatcher {
+    /// Watch the directories containing `paths`, so a file being replaced
+    /// or created is noticed as well as one being written to. Falls back to
+    /// polling if notifications cannot be set up.
+    pub fn new(paths: &[&Path]) -> Self {
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = mpsc::channel();
+        let handler_names = Arc::clone(&names);
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let relevant = match event {
+                // Our own reads show up as access events
+                Ok(event) => {
+                    let names = handler_names.lock().unwrap_or_else(|e| e.into_inner());
+                    !matches!(event.kind, EventKind::Access(_))
+                        && (event.paths.is_empty()
+                            || event.paths.iter().any(|path| names.contains(path)))
+                }
+                Err(_) => true,
+            };
+            if relevant {
+                let _ = sender.send(());
+            }
+        });
+
+        let Ok(watcher) = watcher else {
+            return Self::polling();
+        };
+        let mut this = Self {
+            watcher: Some(watcher),
+            events: Some(receiver),
+            names,
+            directories: Vec::new(),
+        };
+        for path in paths {
+            if this.add(path).is_err() {
+                return Self::polling();
+            }
+        }
+        this
+    }
+
+    /// A watcher that only waits out the poll interval
+    pub fn polling() -> Self {
+        Self {
+            watcher: None,
+            events: None,
+            names: Arc::new(Mutex::new(Vec::new())),
+            directories: Vec::new(),
+        }
+    }
+
+    /// Start watching another file, e.g. one newly matched by a glob
+    pub fn add(&mut self, path: &Path) -> notify::Result<()> {
+        let Some(ref mut watcher) = self.watcher else {
+            return Ok(());
+        };
+        let path = absolute(path);
+        if let Some(directory) = path.parent() {
+            if !self.directories.iter().any(|watched| watched == directory) {
+                watcher.watch(directory, RecursiveMode::NonRecursive)?;
+                self.directories.push(directory.to_path_buf());
+            }
+        }
+        self.names
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(path);
+        Ok(())
+    }
+
+    pub fn is_polling(&self) -> bool {
+        self.events.is_none()
+    }
+
+    /// How long to wait when nothing else is due
+    pub fn idle_timeout(&self) -> Duration {
+        if self.is_polling() {
+            POLL_INTERVAL
+        } else {
+            WATCH_TIMEOUT
+        }
+    }
+
+    /// Block until a watched file changes or `timeout` passes
+    pub fn wait(&self, timeout: Duration) {
+        match &self.events {
+            Some(events) => match events.recv_timeout(timeout) {
+                // Several events usually arrive for one write
+                Ok(()) => while events.try_recv().is_ok() {},
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(timeout),
+            },
+            None => std::thread::sleep(timeout),
+        }
+    }
+}
+
+/// Make a path absolute without resolving symlinks, to compare it with the
+/// paths reported in events
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::time::Instant;
+    use tempfile::NamedTempFile;
+
+    fn append(file: &mut NamedTempFile, text: &str) {
+        file.write_all(text.as_bytes()).unwrap();
+        file.flush().unwrap();
+    }
+
+    #[test]
+    fn test_tail_reads_only_new_lines() {
+        let mut file = NamedTempFile::new().unwrap();
+        append(&mut file, "first\nsecond\r\n");
+
+        let mut tail = FileTail::open(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(tail.read_lines().unwrap().lines, vec!["first", "second"]);
+        assert_eq!(tail.offset(), 14);
+        assert!(tail.read_lines().unwrap().lines.is_empty());
+
+        append(&mut file, "third\n");
+        assert_eq!(tail.read_lines().unwrap().lines, vec!["third"]);
+    }
+
+    #[test]
+    fn test_tail_holds_back_partial_lines() {
+        let mut file = NamedTempFile::new().unwrap();
+        append(&mut file, "complete\npart");
+
+        let mut tail = FileTail::open(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(tail.read_lines().unwrap().lines, vec!["complete"]);
+
+        append(&mut file, "ial");
+        assert!(tail.read_lines().unwrap().lines.is_empty());
+
+        append(&mut file, " line\nnext\n");
+        assert_eq!(
+            tail.read_lines().unwrap().lines,
+            vec!["partial line", "next"]
+        );
+        assert_eq!(tail.offset(), 27);
+    }
+
+    #[test]
+    fn test_tail_detects_truncation() {
+        let mut file = NamedTempFile::new().unwrap();
+        append(&mut file, "old line one\nold line two\n");
+
+        let mut tail = FileTail::open(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(tail.read_lines().unwrap().lines.len(), 2);
+
+        file.as_file().set_len(0).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        append(&mut file, "new\n");
+        let read = tail.read_lines().unwrap();
+        assert_eq!(read.reopened, Some(Reopen::Truncated));
+        assert_eq!(read.lines, vec!["new"]);
+        assert_eq!(tail.read_lines().unwrap(), TailRead::default());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tail_follows_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let rotated = dir.path().join("app.log.1");
+        std::fs::write(&path, "first\n").unwrap();
+
+        let mut tail = FileTail::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(tail.read_lines().unwrap().lines, vec!["first"]);
+
+        // Written just before and after the rename, then a fresh file appears
+        let mut old = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        old.write_all(b"second\n").unwrap();
+        std::fs::rename(&path, &rotated).unwrap();
+        old.write_all(b"third\nunfinished").unwrap();
+
+        // Nothing is lost while the path is missing
+        let read = tail.read_lines().unwrap();
+        assert_eq!(read.lines, vec!["second", "third"]);
+        assert_eq!(read.reopened, None);
+
+        std::fs::write(&path, "fresh\n").unwrap();
+        let read = tail.read_lines().unwrap();
+        assert_eq!(read.reopened, Some(Reopen::Rotated));
+        assert_eq!(read.lines, vec!["unfinished", "fresh"]);
+        assert_eq!(tail.offset(), 6);
+    }
+
+    #[test]
+    fn test_tail_catches_up_in_bounded_reads() {
+        let mut file = NamedTempFile::new().unwrap();
+        let line = format!("{}\n", "x".repeat(99));
+        let count = 3 * MAX_READ_BYTES as usize / line.len();
+        append(&mut file, &line.repeat(count));
+
+        let mut tail = FileTail::open(file.path().to_str().unwrap()).unwrap();
+        let mut reads = Vec::new();
+        loop {
+            let lines = tail.read_lines().unwrap().lines;
+            if lines.is_empty() {
+                break;
+            }
+            reads.push(lines.len());
+        }
+        assert!(reads.len() >= 3, "{:?}", reads);
+        assert!(reads
+            .iter()
+            .all(|&lines| lines * line.len() <= MAX_READ_BYTES as usize + line.len()));
+        assert_eq!(reads.iter().sum::<usize>(), count);
+        assert_eq!(tail.offset(), (count * line.len()) as u64);
+    }
+
+    #[test]
+    fn test_tail_rejects_compressed_files() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&[0x1f, 0x8b, 0x08, 0x00]).unwrap();
+        assert!(FileTail::open(file.path().to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_watcher_wakes_for_added_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = ChangeWatcher::new(&[]);
+        if watcher.is_polling() {
+            return;
+        }
+
+        let path = dir.path().join("later.log");
+        watcher.add(&path).unwrap();
+        std::fs::write(&path, "line\n").unwrap();
+        let start = Instant::now();
+        watcher.wait(Duration::from_secs(5));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_watcher_wakes_on_write() {
+        let mut file = NamedTempFile::new().unwrap();
+        let watcher = ChangeWatcher::new(&[file.path()]);
+        if watcher.is_polling() {
+            return;
+        }
+
+        append(&mut file, "line\n");
+        let start = Instant::now();
+        watcher.wait(Duration::from_secs(5));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
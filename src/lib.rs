@@ -1,9 +1,15 @@
+pub mod aggregate;
 pub mod bucket;
 pub mod cli;
+pub mod filter;
+pub mod follow;
 pub mod output;
 pub mod plot;
 pub mod processor;
+pub mod range;
 pub mod reader;
+pub mod structured;
 pub mod timestamp;
+pub mod timezone;
 
 pub use cli::Args;
@@ -1,14 +1,23 @@
+use crate::aggregate::{format_value, AggFn};
+use crate::bucket::{BucketSize, SeriesRow, ValueRow};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde::Serialize;
 
 #[derive(Serialize)]
 struct BucketEntry {
     timestamp: String,
+    /// Exclusive end of the bucket; calendar buckets vary in length
+    end: String,
     count: usize,
 }
 
-pub fn output_table(buckets: &[(DateTime<Utc>, usize)], bucket_size_seconds: f64) -> Result<()> {
+pub fn output_table(
+    buckets: &[(DateTime<Utc>, usize)],
+    bucket_size: &BucketSize,
+    tz: &Tz,
+) -> Result<()> {
     if buckets.is_empty() {
         println!("No matches found.");
         return Ok(());
@@ -22,25 +31,26 @@ pub fn output_table(buckets: &[(DateTime<Utc>, usize)], bucket_size_seconds: f64
     for (timestamp, count) in buckets {
         println!(
             "{:30} | {:>10}",
-            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            timestamp.with_timezone(tz).format("%Y-%m-%d %H:%M:%S"),
             count
         );
     }
 
     println!("{:-^30}-+-{:-^10}", "", "");
     println!("{:30} | {:>10}", "Total", total);
-    if bucket_size_seconds < 1.0 {
-        println!("\nBucket size: {:.3} seconds", bucket_size_seconds);
-    } else if bucket_size_seconds == bucket_size_seconds.floor() {
-        println!("\nBucket size: {} seconds", bucket_size_seconds as i64);
-    } else {
-        println!("\nBucket size: {:.3} seconds", bucket_size_seconds);
+    println!("\nBucket size: {}", bucket_size);
+    if *tz != Tz::UTC {
+        println!("Time zone: {}", tz.name());
     }
 
     Ok(())
 }
 
-pub fn output_csv(buckets: &[(DateTime<Utc>, usize)], exclude_headers: bool) -> Result<()> {
+pub fn output_csv(
+    buckets: &[(DateTime<Utc>, usize)],
+    tz: &Tz,
+    exclude_headers: bool,
+) -> Result<()> {
     let mut wtr = csv::Writer::from_writer(std::io::stdout());
 
     if !exclude_headers {
@@ -48,7 +58,7 @@ pub fn output_csv(buckets: &[(DateTime<Utc>, usize)], exclude_headers: bool) ->
     }
 
     for (timestamp, count) in buckets {
-        wtr.write_record(&[timestamp.to_rfc3339(), count.to_string()])?;
+        wtr.write_record(&[timestamp.with_timezone(tz).to_rfc3339(), count.to_string()])?;
     }
 
     wtr.flush()?;
@@ -57,33 +67,481 @@ pub fn output_csv(buckets: &[(DateTime<Utc>, usize)], exclude_headers: bool) ->
 
 pub fn output_json(
     buckets: &[(DateTime<Utc>, usize)],
-    bucket_size_seconds: f64,
+    bucket_size: &BucketSize,
+    tz: &Tz,
     time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    timestamp_formats: &[(String, String)],
 ) -> Result<()> {
     let entries: Vec<BucketEntry> = buckets
         .iter()
-        .map(|(ts, count)| BucketEntry {
-            timestamp: ts.to_rfc3339(),
-            count: *count,
-        })
+        .map(|(ts, count)| bucket_entry(*ts, *count, bucket_size, tz))
         .collect();
 
     let total: usize = buckets.iter().map(|(_, count)| count).sum();
 
-    let output = serde_json::json!({
-        "buckets": entries,
+    let mut output = json_summary(total, bucket_size, tz, time_range, timestamp_formats);
+    output.insert("buckets".to_string(), serde_json::to_value(entries)?);
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// Table with one count column per series
+pub fn output_series_table(
+    series: &[String],
+    rows: &[SeriesRow],
+    bucket_size: &BucketSize,
+    tz: &Tz,
+) -> Result<()> {
+    if rows.is_empty() {
+        println!("No matches found.");
+        return Ok(());
+    }
+
+    let mut totals = vec![0; series.len()];
+    let labelled: Vec<(String, Vec<String>)> = rows
+        .iter()
+        .map(|(timestamp, counts)| {
+            for (total, count) in totals.iter_mut().zip(counts) {
+                *total += count;
+            }
+            let label = timestamp.with_timezone(tz).format("%Y-%m-%d %H:%M:%S");
+            (
+                label.to_string(),
+                counts.iter().map(|c| c.to_string()).collect(),
+            )
+        })
+        .collect();
+    let totals: Vec<String> = totals.iter().map(|t| t.to_string()).collect();
+    print_table(series, &labelled, &totals);
+
+    println!("\nBucket size: {}", bucket_size);
+    if *tz != Tz::UTC {
+        println!("Time zone: {}", tz.name());
+    }
+
+    Ok(())
+}
+
+/// CSV with a `timestamp` column followed by one column per series
+pub fn output_series_csv(
+    series: &[String],
+    rows: &[SeriesRow],
+    tz: &Tz,
+    exclude_headers: bool,
+) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(std::io::stdout());
+
+    if !exclude_headers {
+        let mut header = vec!["timestamp"];
+        header.extend(series.iter().map(String::as_str));
+        wtr.write_record(&header)?;
+    }
+
+    for (timestamp, counts) in rows {
+        let mut record = vec![timestamp.with_timezone(tz).to_rfc3339()];
+        record.extend(counts.iter().map(|c| c.to_string()));
+        wtr.write_record(&record)?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+/// JSON with a `series` array, each entry holding its own buckets and total.
+/// `total` is the number of matching lines, which a line matching several
+/// series adds to only once.
+pub fn output_series_json(
+    series: &[String],
+    rows: &[SeriesRow],
+    total: usize,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    timestamp_formats: &[(String, String)],
+) -> Result<()> {
+    let entries: Vec<serde_json::Value> = series
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let buckets: Vec<BucketEntry> = rows
+                .iter()
+                .map(|(ts, counts)| bucket_entry(*ts, counts[index], bucket_size, tz))
+                .collect();
+            let total: usize = rows.iter().map(|(_, counts)| counts[index]).sum();
+            serde_json::json!({
+                "name": name,
+                "total": total,
+                "buckets": buckets,
+            })
+        })
+        .collect();
+
+    let mut output = json_summary(total, bucket_size, tz, time_range, timestamp_formats);
+    output.insert("series".to_string(), serde_json::Value::Array(entries));
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// JSON for `--group-by`: per-group totals and, in every bucket, a `groups`
+/// object mapping each key to its count
+#[allow(clippy::too_many_arguments)]
+pub fn output_grouped_json(
+    group_by: &str,
+    groups: &[String],
+    rows: &[SeriesRow],
+    total: usize,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    timestamp_formats: &[(String, String)],
+) -> Result<()> {
+    let mut totals = serde_json::Map::new();
+    for (index, key) in groups.iter().enumerate() {
+        let total: usize = rows.iter().map(|(_, counts)| counts[index]).sum();
+        totals.insert(key.clone(), total.into());
+    }
+
+    let buckets: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|(ts, counts)| {
+            let groups: serde_json::Map<String, serde_json::Value> = groups
+                .iter()
+                .zip(counts)
+                .map(|(key, count)| (key.clone(), (*count).into()))
+                .collect();
+            let mut entry =
+                serde_json::to_value(bucket_entry(*ts, counts.iter().sum(), bucket_size, tz))?;
+            entry["groups"] = serde_json::Value::Object(groups);
+            Ok(entry)
+        })
+        .collect::<Result<_>>()?;
+
+    let mut output = json_summary(total, bucket_size, tz, time_range, timestamp_formats);
+    output.insert("group_by".to_string(), group_by.into());
+    output.insert("groups".to_string(), serde_json::Value::Object(totals));
+    output.insert("buckets".to_string(), serde_json::Value::Array(buckets));
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// Table of `--value` aggregates. A single series shows its match count next
+/// to the value; several series show one value column each.
+pub fn output_value_table(
+    series: &[String],
+    counts: &[SeriesRow],
+    values: &[ValueRow],
+    totals: &[Option<f64>],
+    agg: AggFn,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+) -> Result<()> {
+    if values.is_empty() {
+        println!("No matches found.");
+        return Ok(());
+    }
+
+    let single = series.is_empty();
+    let columns: Vec<String> = if single {
+        vec!["Count".to_string(), agg.to_string()]
+    } else {
+        series.to_vec()
+    };
+    let cells = |counts: &[usize], values: &[Option<f64>]| -> Vec<String> {
+        let mut cells = Vec::new();
+        if single {
+            cells.push(counts[0].to_string());
+        }
+        cells.extend(values.iter().map(|value| format_cell(*value)));
+        cells
+    };
+
+    let rows: Vec<(String, Vec<String>)> = counts
+        .iter()
+        .zip(values)
+        .map(|((timestamp, counts), (_, values))| {
+            let label = timestamp.with_timezone(tz).format("%Y-%m-%d %H:%M:%S");
+            (label.to_string(), cells(counts, values))
+        })
+        .collect();
+    let count_totals: Vec<usize> = vec![counts.iter().map(|(_, c)| c[0]).sum()];
+    print_table(&columns, &rows, &cells(&count_totals, totals));
+
+    println!("\nBucket size: {}", bucket_size);
+    println!("Aggregate: {}", agg);
+    if *tz != Tz::UTC {
+        println!("Time zone: {}", tz.name());
+    }
+
+    Ok(())
+}
+
+/// CSV of `--value` aggregates, laid out like `output_value_table`; buckets
+/// without values have an empty cell
+pub fn output_value_csv(
+    series: &[String],
+    counts: &[SeriesRow],
+    values: &[ValueRow],
+    agg: AggFn,
+    tz: &Tz,
+    exclude_headers: bool,
+) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(std::io::stdout());
+    let single = series.is_empty();
+
+    if !exclude_headers {
+        let mut header = vec!["timestamp".to_string()];
+        if single {
+            header.extend(["count".to_string(), agg.to_string()]);
+        } else {
+            header.extend(series.iter().cloned());
+        }
+        wtr.write_record(&header)?;
+    }
+
+    for ((timestamp, counts), (_, values)) in counts.iter().zip(values) {
+        let mut record = vec![timestamp.with_timezone(tz).to_rfc3339()];
+        if single {
+            record.push(counts[0].to_string());
+        }
+        record.extend(
+            values
+                .iter()
+                .map(|value| value.map(format_value).unwrap_or_default()),
+        );
+        wtr.write_record(&record)?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+/// JSON of `--value` aggregates. Every bucket has a `count` and a `value`
+/// (null without values); several series are listed in a `series` array.
+#[allow(clippy::too_many_arguments)]
+pub fn output_value_json(
+    series: &[String],
+    counts: &[SeriesRow],
+    total: usize,
+    values: &[ValueRow],
+    totals: &[Option<f64>],
+    agg: AggFn,
+    group_by: Option<&str>,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    timestamp_formats: &[(String, String)],
+) -> Result<()> {
+    let buckets = |index: usize| -> Result<Vec<serde_json::Value>> {
+        counts
+            .iter()
+            .zip(values)
+            .map(|((ts, counts), (_, values))| {
+                let mut entry =
+                    serde_json::to_value(bucket_entry(*ts, counts[index], bucket_size, tz))?;
+                entry["value"] = values[index].into();
+                Ok(entry)
+            })
+            .collect()
+    };
+    let total_of = |index: usize| -> usize { counts.iter().map(|(_, counts)| counts[index]).sum() };
+
+    let mut output = json_summary(total, bucket_size, tz, time_range, timestamp_formats);
+    output.insert("aggregate".to_string(), agg.to_string().into());
+    if let Some(group) = group_by {
+        output.insert("group_by".to_string(), group.into());
+    }
+
+    if series.is_empty() {
+        output.insert("value".to_string(), totals[0].into());
+        output.insert("buckets".to_string(), buckets(0)?.into());
+    } else {
+        let entries: Vec<serde_json::Value> = series
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                Ok(serde_json::json!({
+                    "name": name,
+                    "total": total_of(index),
+                    "value": totals[index],
+                    "buckets": buckets(index)?,
+                }))
+            })
+            .collect::<Result<_>>()?;
+        output.insert("series".to_string(), entries.into());
+    }
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// Print a table with a timestamp column, one column per entry of `columns`
+/// and a closing "Total" row
+fn print_table(columns: &[String], rows: &[(String, Vec<String>)], totals: &[String]) {
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|name| name.chars().count().max(10))
+        .collect();
+    let print_row = |label: &str, values: &[String]| {
+        print!("{:30}", label);
+        for (value, width) in values.iter().zip(&widths) {
+            print!(" | {:>width$}", value, width = width);
+        }
+        println!();
+    };
+    let print_separator = || {
+        print!("{:-^30}", "");
+        for width in &widths {
+            print!("-+-{}", "-".repeat(*width));
+        }
+        println!();
+    };
+
+    println!();
+    print_row(&format!("{:^30}", "Timestamp"), columns);
+    print_separator();
+    for (label, values) in rows {
+        print_row(label, values);
+    }
+    print_separator();
+    print_row("Total", totals);
+}
+
+/// Header for `--stream` CSV: the columns of the batch CSV, plus
+/// `provisional` when updates for the open bucket are emitted too
+pub fn stream_csv_header(columns: &[String], provisional: bool) -> Result<()> {
+    let mut header = vec!["timestamp"];
+    header.extend(columns.iter().map(String::as_str));
+    if provisional {
+        header.push("provisional");
+    }
+    write_stream_csv(&header)
+}
+
+/// One `--stream` CSV row, flushed so a reader sees it as soon as the bucket
+/// closes. `provisional` is None unless open buckets are reported.
+pub fn stream_csv_row(
+    start: DateTime<Utc>,
+    counts: &[usize],
+    tz: &Tz,
+    provisional: Option<bool>,
+) -> Result<()> {
+    let mut record = vec![start.with_timezone(tz).to_rfc3339()];
+    record.extend(counts.iter().map(|c| c.to_string()));
+    record.extend(provisional.map(|p| p.to_string()));
+    write_stream_csv(&record)
+}
+
+fn write_stream_csv<T: AsRef<[u8]>>(record: &[T]) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(std::io::stdout().lock());
+    wtr.write_record(record)?;
+    wtr.flush()?;
+    Ok(())
+}
+
+/// One `--stream` NDJSON line, flushed like `stream_csv_row`. `breakdown`
+/// names the key ("series" or "groups") under which each series' count is
+/// listed when there are several.
+pub fn stream_json_row(
+    start: DateTime<Utc>,
+    counts: &[usize],
+    breakdown: Option<(&str, &[String])>,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    provisional: Option<bool>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let entry = stream_json_entry(start, counts, breakdown, bucket_size, tz, provisional)?;
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", serde_json::to_string(&entry)?)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn stream_json_entry(
+    start: DateTime<Utc>,
+    counts: &[usize],
+    breakdown: Option<(&str, &[String])>,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    provisional: Option<bool>,
+) -> Result<serde_json::Value> {
+    let total = counts.iter().sum();
+    let mut entry = serde_json::to_value(bucket_entry(start, total, bucket_size, tz))?;
+    if let Some((key, names)) = breakdown {
+        let counts: serde_json::Map<String, serde_json::Value> = names
+            .iter()
+            .zip(counts)
+            .map(|(name, count)| (name.clone(), (*count).into()))
+            .collect();
+        entry[key] = serde_json::Value::Object(counts);
+    }
+    if let Some(provisional) = provisional {
+        entry["provisional"] = provisional.into();
+    }
+    Ok(entry)
+}
+
+/// A table cell for an aggregated value; "-" for a bucket without values
+fn format_cell(value: Option<f64>) -> String {
+    value.map(format_value).unwrap_or_else(|| "-".to_string())
+}
+
+fn bucket_entry(
+    start: DateTime<Utc>,
+    count: usize,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+) -> BucketEntry {
+    BucketEntry {
+        timestamp: start.with_timezone(tz).to_rfc3339(),
+        end: bucket_size
+            .bucket_end(start, tz)
+            .with_timezone(tz)
+            .to_rfc3339(),
+        count,
+    }
+}
+
+/// Fields shared by every JSON output. `timestamp_formats` holds the
+/// timestamp format detected in each source.
+fn json_summary(
+    total: usize,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    timestamp_formats: &[(String, String)],
+) -> serde_json::Map<String, serde_json::Value> {
+    // Calendar buckets have no fixed width in seconds
+    let bucket_size_seconds = match bucket_size {
+        BucketSize::Calendar(..) => None,
+        size => Some(size.nominal_seconds()),
+    };
+
+    let summary = serde_json::json!({
         "total_matches": total,
         "bucket_size_seconds": bucket_size_seconds,
+        "bucket_size": bucket_size.to_string(),
+        "timezone": tz.name(),
         "time_range": time_range.map(|(start, end)| {
             serde_json::json!({
-                "start": start.to_rfc3339(),
-                "end": end.to_rfc3339(),
+                "start": start.with_timezone(tz).to_rfc3339(),
+                "end": end.with_timezone(tz).to_rfc3339(),
             })
         }),
+        "timestamp_formats": timestamp_formats
+            .iter()
+            .map(|(source, format)| (source.clone(), format.clone().into()))
+            .collect::<serde_json::Map<String, serde_json::Value>>(),
     });
 
-    println!("{}", serde_json::to_string_pretty(&output)?);
-    Ok(())
+    match summary {
+        serde_json::Value::Object(map) => map,
+        _ => unreachable!(),
+    }
 }
 
 #[cfg(test)]
@@ -102,28 +560,28 @@ mod tests {
     #[test]
     fn test_output_table_with_data() {
         let buckets = create_test_buckets();
-        let result = output_table(&buckets, 60.0);
+        let result = output_table(&buckets, &BucketSize::Seconds(60.0), &Tz::UTC);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_output_table_empty() {
         let buckets = vec![];
-        let result = output_table(&buckets, 60.0);
+        let result = output_table(&buckets, &BucketSize::Seconds(60.0), &Tz::UTC);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_output_csv_with_data() {
         let buckets = create_test_buckets();
-        let result = output_csv(&buckets, false);
+        let result = output_csv(&buckets, &Tz::UTC, false);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_output_csv_empty() {
         let buckets = vec![];
-        let result = output_csv(&buckets, true);
+        let result = output_csv(&buckets, &Tz::UTC, true);
         assert!(result.is_ok());
     }
 
@@ -132,28 +590,48 @@ mod tests {
         let buckets = create_test_buckets();
         let start = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
         let end = Utc.with_ymd_and_hms(2025, 10, 3, 12, 2, 0).unwrap();
-        let result = output_json(&buckets, 60.0, Some((start, end)));
+        let result = output_json(
+            &buckets,
+            &BucketSize::Seconds(60.0),
+            &Tz::UTC,
+            Some((start, end)),
+            &[],
+        );
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_output_json_without_time_range() {
         let buckets = create_test_buckets();
-        let result = output_json(&buckets, 60.0, None);
+        let result = output_json(&buckets, &BucketSize::Seconds(60.0), &Tz::UTC, None, &[]);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_output_json_empty() {
         let buckets = vec![];
-        let result = output_json(&buckets, 60.0, None);
+        let result = output_json(&buckets, &BucketSize::Seconds(60.0), &Tz::UTC, None, &[]);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_output_calendar_buckets() {
+        use crate::bucket::CalendarUnit;
+
+        let buckets = vec![
+            (Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(), 4),
+            (Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap(), 2),
+        ];
+        let size = BucketSize::Calendar(CalendarUnit::Month, 1);
+        assert!(output_table(&buckets, &size, &Tz::UTC).is_ok());
+        assert!(output_json(&buckets, &size, &Tz::UTC, None, &[]).is_ok());
+    }
+
     #[test]
     fn test_bucket_entry_serialization() {
         let entry = BucketEntry {
             timestamp: "2025-10-03T12:00:00Z".to_string(),
+            end: "2025-10-03T12:01:00Z".to_string(),
             count: 42,
         };
 
@@ -171,6 +649,7 @@ mod tests {
             .iter()
             .map(|(ts, count)| BucketEntry {
                 timestamp: ts.to_rfc3339(),
+                end: (*ts + chrono::Duration::seconds(60)).to_rfc3339(),
                 count: *count,
             })
             .collect();
@@ -189,4 +668,55 @@ mod tests {
         assert!(output["buckets"].is_array());
         assert_eq!(output["buckets"].as_array().unwrap().len(), 1);
     }
+
+    #[test]
+    fn test_output_series() {
+        let series = vec!["ERROR".to_string(), "a_much_longer_series_name".to_string()];
+        let rows = vec![
+            (
+                Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap(),
+                vec![3, 0],
+            ),
+            (
+                Utc.with_ymd_and_hms(2025, 10, 3, 12, 1, 0).unwrap(),
+                vec![1, 2],
+            ),
+        ];
+        let size = BucketSize::Seconds(60.0);
+        assert!(output_series_table(&series, &rows, &size, &Tz::UTC).is_ok());
+        assert!(output_series_table(&series, &[], &size, &Tz::UTC).is_ok());
+        assert!(output_series_csv(&series, &rows, &Tz::UTC, false).is_ok());
+        assert!(output_series_json(&series, &rows, 5, &size, &Tz::UTC, None, &[]).is_ok());
+        assert!(output_grouped_json("code", &series, &rows, 6, &size, &Tz::UTC, None, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_stream_json_entry() {
+        let start = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let size = BucketSize::Seconds(60.0);
+
+        let entry = stream_json_entry(start, &[4], None, &size, &Tz::UTC, None).unwrap();
+        assert_eq!(
+            entry,
+            serde_json::json!({
+                "timestamp": "2025-10-03T12:00:00+00:00",
+                "end": "2025-10-03T12:01:00+00:00",
+                "count": 4,
+            })
+        );
+
+        let names = vec!["500".to_string(), "404".to_string()];
+        let entry = stream_json_entry(
+            start,
+            &[3, 1],
+            Some(("groups", &names)),
+            &size,
+            &Tz::UTC,
+            Some(true),
+        )
+        .unwrap();
+        assert_eq!(entry["count"], 4);
+        assert_eq!(entry["groups"], serde_json::json!({"500": 3, "404": 1}));
+        assert_eq!(entry["provisional"], true);
+    }
 }
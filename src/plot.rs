@@ -1,22 +1,160 @@
+use crate::aggregate::format_value;
+use crate::bucket::{BucketSize, SeriesRow, ValueRow};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use image::{ImageBuffer, Rgb};
 use plotters::backend::BitMapBackend;
 use plotters::prelude::*;
+use rgb::RGB8;
 use terminal_size::{terminal_size, Width};
 use textplots::{
-    AxisBuilder, Chart, LabelBuilder, LabelFormat, Plot, Shape, TickDisplay, TickDisplayBuilder,
+    AxisBuilder, Chart, ColorPlot, LabelBuilder, LabelFormat, Plot, Shape, TickDisplay,
+    TickDisplayBuilder,
 };
 
+/// Line colours for multi-series charts, shared by ASCII and PNG output
+const SERIES_COLORS: [(u8, u8, u8); 8] = [
+    (0, 0, 255),
+    (220, 50, 47),
+    (0, 160, 0),
+    (230, 140, 0),
+    (150, 50, 200),
+    (0, 170, 190),
+    (200, 0, 130),
+    (120, 120, 120),
+];
+
+fn series_color(index: usize) -> (u8, u8, u8) {
+    SERIES_COLORS[index % SERIES_COLORS.len()]
+}
+
+/// Y-axis label of match count charts
+const COUNT_LABEL: &str = "Match count";
+
+/// One line of a chart: its legend label and one value per bucket, None
+/// where the bucket has no value
+struct ChartLine<'a> {
+    label: &'a str,
+    values: Vec<Option<f64>>,
+}
+
+impl<'a> ChartLine<'a> {
+    fn counts(label: &'a str, counts: impl Iterator<Item = usize>) -> Self {
+        Self {
+            label,
+            values: counts.map(|count| Some(count as f64)).collect(),
+        }
+    }
+}
+
 pub fn plot_ascii(
     buckets: &[(DateTime<Utc>, usize)],
     time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
-    bucket_size_seconds: f64,
+    bucket_size: &BucketSize,
+    tz: &Tz,
     pattern: &str,
     _files: &[String],
     y_zero: bool,
 ) -> Result<()> {
-    if buckets.is_empty() {
+    let starts: Vec<DateTime<Utc>> = buckets.iter().map(|(ts, _)| *ts).collect();
+    let line = ChartLine::counts(pattern, buckets.iter().map(|(_, count)| *count));
+    let title = format!("Pattern: \"{}\"", pattern);
+    plot_ascii_lines(
+        &starts,
+        &[line],
+        time_range,
+        bucket_size,
+        tz,
+        &title,
+        COUNT_LABEL,
+        _files,
+        y_zero,
+    )
+}
+
+/// ASCII chart with one coloured line per series and a legend
+pub fn plot_ascii_series(
+    series: &[String],
+    rows: &[SeriesRow],
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    files: &[String],
+    y_zero: bool,
+) -> Result<()> {
+    let starts: Vec<DateTime<Utc>> = rows.iter().map(|(ts, _)| *ts).collect();
+    let lines: Vec<ChartLine> = series
+        .iter()
+        .enumerate()
+        .map(|(index, name)| ChartLine::counts(name, rows.iter().map(|(_, counts)| counts[index])))
+        .collect();
+    let title = format!("Series: {}", series.join(", "));
+    plot_ascii_lines(
+        &starts,
+        &lines,
+        time_range,
+        bucket_size,
+        tz,
+        &title,
+        COUNT_LABEL,
+        files,
+        y_zero,
+    )
+}
+
+/// ASCII chart of `--value` aggregates, one line per series
+#[allow(clippy::too_many_arguments)]
+pub fn plot_ascii_values(
+    series: &[String],
+    rows: &[ValueRow],
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    title: &str,
+    y_label: &str,
+    files: &[String],
+    y_zero: bool,
+) -> Result<()> {
+    let starts: Vec<DateTime<Utc>> = rows.iter().map(|(ts, _)| *ts).collect();
+    let lines = value_lines(series, rows);
+    plot_ascii_lines(
+        &starts,
+        &lines,
+        time_range,
+        bucket_size,
+        tz,
+        title,
+        y_label,
+        files,
+        y_zero,
+    )
+}
+
+fn value_lines<'a>(series: &'a [String], rows: &[ValueRow]) -> Vec<ChartLine<'a>> {
+    series
+        .iter()
+        .enumerate()
+        .map(|(index, name)| ChartLine {
+            label: name,
+            values: rows.iter().map(|(_, values)| values[index]).collect(),
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn plot_ascii_lines(
+    starts: &[DateTime<Utc>],
+    lines: &[ChartLine],
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    title: &str,
+    y_label: &str,
+    _files: &[String],
+    y_zero: bool,
+) -> Result<()> {
+    if starts.is_empty() {
         println!("No data to plot.");
         return Ok(());
     }
@@ -25,14 +163,19 @@ pub fn plot_ascii(
         let range_seconds = (last.timestamp() - first.timestamp()) as f32;
         (first, last, range_seconds)
     } else {
-        let (first, _) = buckets.first().unwrap();
-        let (last, _) = buckets.last().unwrap();
+        let first = starts.first().unwrap();
+        let last = starts.last().unwrap();
         let range_seconds = (last.timestamp() - first.timestamp()) as f32;
         (*first, *last, range_seconds)
     };
 
     // Calculate dimensions
-    let max_count = buckets.iter().map(|(_, c)| c).max().unwrap_or(&0);
+    let all_values = || {
+        lines
+            .iter()
+            .flat_map(|line| line.values.iter().flatten().copied())
+    };
+    let max_value = all_values().fold(0.0, f64::max);
     let duration_hours = time_range_seconds / 3600.0;
 
     // Format files list
@@ -45,72 +188,74 @@ pub fn plot_ascii(
     };
 
     // Convert to points for textplots using bucket indices
-    let points: Vec<(f32, f32)> = buckets
+    let points: Vec<Vec<(f32, f32)>> = lines
         .iter()
-        .enumerate()
-        .map(|(i, (_, count))| (i as f32, *count as f32))
+        .map(|line| {
+            line.values
+                .iter()
+                .enumerate()
+                .filter_map(|(i, value)| value.map(|value| (i as f32, value as f32)))
+                .collect()
+        })
         .collect();
 
     let x_min = 0.0;
-    let x_max = (points.len().saturating_sub(1)) as f32;
+    let x_max = (starts.len().saturating_sub(1)) as f32;
 
     // Calculate Y-axis range
     let y_min = if y_zero {
         0.0
     } else {
-        let min_count = buckets.iter().map(|(_, c)| *c).min().unwrap_or(0) as f32;
-        (min_count * 0.9).max(0.0) // 10% padding below min, but not negative
+        let min_value = all_values().reduce(f64::min).unwrap_or(0.0) as f32;
+        (min_value * 0.9).max(0.0) // 10% padding below min, but not negative
     };
-    let y_max = *max_count as f32;
+    let y_max = max_value as f32;
 
     // Build and display chart
-    render_chart(
-        &points,
-        x_min,
-        x_max,
-        y_min,
-        y_max,
-        pattern,
-        bucket_size_seconds,
-        &files_str,
-    );
+    render_chart(&points, x_min, x_max, y_min, y_max, title, &files_str);
+
+    // A single series keeps the terminal's default colour and needs no legend
+    if lines.len() > 1 {
+        let legend: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let (r, g, b) = series_color(index);
+                format!("\x1B[38;2;{};{};{}m━━\x1B[0m {}", r, g, b, line.label)
+            })
+            .collect();
+        println!("Legend: {}", legend.join("  "));
+    }
 
-    let bucket_count = buckets.len();
+    let bucket_count = starts.len();
 
     // Enhanced x-axis information
+    let bucket_size_seconds = bucket_size.nominal_seconds();
+    let bucket_label = bucket_size.to_string();
     if bucket_size_seconds < 1.0 {
         println!(
-            "X-axis: Time offset (0-{:.1}s) | Buckets: {} ({:.1}s each)",
-            time_range_seconds, bucket_count, bucket_size_seconds
-        );
-    } else if bucket_size_seconds < 60.0 {
-        println!(
-            "X-axis: Time offset (0-{:.0}s) | Buckets: {} ({:.0}s each)",
-            time_range_seconds, bucket_count, bucket_size_seconds
+            "X-axis: Time offset (0-{:.1}s) | Buckets: {} ({} each)",
+            time_range_seconds, bucket_count, bucket_label
         );
     } else if bucket_size_seconds < 3600.0 {
         println!(
-            "X-axis: Time offset (0-{:.0}s) | Buckets: {} ({:.0}m each)",
-            time_range_seconds,
-            bucket_count,
-            bucket_size_seconds / 60.0
+            "X-axis: Time offset (0-{:.0}s) | Buckets: {} ({} each)",
+            time_range_seconds, bucket_count, bucket_label
         );
     } else {
         println!(
-            "X-axis: Time offset (0-{:.1}h) | Buckets: {} ({:.1}h each)",
-            duration_hours,
-            bucket_count,
-            bucket_size_seconds / 3600.0
+            "X-axis: Time offset (0-{:.1}h) | Buckets: {} ({} each)",
+            duration_hours, bucket_count, bucket_label
         );
     }
 
-    println!("Y-axis: Match count (max: {})", max_count);
+    println!("Y-axis: {} (max: {})", y_label, format_value(max_value));
 
     // Show time range
     println!(
         "Time range: {} to {}",
-        first_ts.format("%Y-%m-%d %H:%M:%S"),
-        last_ts.format("%Y-%m-%d %H:%M:%S")
+        first_ts.with_timezone(tz).format("%Y-%m-%d %H:%M:%S"),
+        last_ts.with_timezone(tz).format("%Y-%m-%d %H:%M:%S %Z")
     );
 
     Ok(())
@@ -127,13 +272,12 @@ fn chart_width_for_terminal() -> u32 {
 
 #[allow(clippy::too_many_arguments)]
 fn render_chart(
-    points: &[(f32, f32)],
+    lines: &[Vec<(f32, f32)>],
     x_min: f32,
     x_max: f32,
     y_min: f32,
     y_max: f32,
-    pattern: &str,
-    _bucket_size_seconds: f64,
+    title: &str,
     files_str: &str,
 ) {
     let term = console::Term::stdout();
@@ -147,10 +291,20 @@ fn render_chart(
     term.clear_screen().unwrap();
     term.move_cursor_to(0, 0).unwrap();
 
-    println!("\nPattern: \"{}\" | Files: {}\n", pattern, files_str);
+    println!("\n{} | Files: {}\n", title, files_str);
 
-    Chart::new_with_y_range(chart_width, chart_height, x_min, x_max, y_min, y_max)
-        .lineplot(&Shape::Lines(points))
+    let shapes: Vec<Shape> = lines.iter().map(|points| Shape::Lines(points)).collect();
+    let mut chart = Chart::new_with_y_range(chart_width, chart_height, x_min, x_max, y_min, y_max);
+    let mut chart = &mut chart;
+    if let [shape] = shapes.as_slice() {
+        chart = chart.lineplot(shape);
+    } else {
+        for (index, shape) in shapes.iter().enumerate() {
+            let (r, g, b) = series_color(index);
+            chart = chart.linecolorplot(shape, RGB8::new(r, g, b));
+        }
+    }
+    chart
         .x_axis_style(textplots::LineStyle::Solid)
         .y_axis_style(textplots::LineStyle::Solid)
         .y_tick_display(TickDisplay::Sparse)
@@ -161,8 +315,63 @@ fn render_chart(
     term.show_cursor().unwrap();
 }
 
-pub fn plot_png(buckets: &[(DateTime<Utc>, usize)], output_file: &str) -> Result<()> {
-    if buckets.is_empty() {
+pub fn plot_png(
+    buckets: &[(DateTime<Utc>, usize)],
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    output_file: &str,
+) -> Result<()> {
+    let starts: Vec<DateTime<Utc>> = buckets.iter().map(|(ts, _)| *ts).collect();
+    let line = ChartLine::counts("Matches", buckets.iter().map(|(_, count)| *count));
+    draw_png(&starts, &[line], bucket_size, tz, "Count", output_file)
+}
+
+/// PNG chart with one coloured line per series and a legend
+pub fn plot_png_series(
+    series: &[String],
+    rows: &[SeriesRow],
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    output_file: &str,
+) -> Result<()> {
+    let starts: Vec<DateTime<Utc>> = rows.iter().map(|(ts, _)| *ts).collect();
+    let lines: Vec<ChartLine> = series
+        .iter()
+        .enumerate()
+        .map(|(index, name)| ChartLine::counts(name, rows.iter().map(|(_, counts)| counts[index])))
+        .collect();
+    draw_png(&starts, &lines, bucket_size, tz, "Count", output_file)
+}
+
+/// PNG chart of `--value` aggregates, one line per series
+pub fn plot_png_values(
+    series: &[String],
+    rows: &[ValueRow],
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    y_label: &str,
+    output_file: &str,
+) -> Result<()> {
+    let starts: Vec<DateTime<Utc>> = rows.iter().map(|(ts, _)| *ts).collect();
+    draw_png(
+        &starts,
+        &value_lines(series, rows),
+        bucket_size,
+        tz,
+        y_label,
+        output_file,
+    )
+}
+
+fn draw_png(
+    starts: &[DateTime<Utc>],
+    lines: &[ChartLine],
+    bucket_size: &BucketSize,
+    tz: &Tz,
+    y_label: &str,
+    output_file: &str,
+) -> Result<()> {
+    if starts.is_empty() {
         anyhow::bail!("No data to plot.");
     }
 
@@ -175,9 +384,23 @@ pub fn plot_png(buckets: &[(DateTime<Utc>, usize)], output_file: &str) -> Result
         let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
         root.fill(&WHITE)?;
 
-        let max_count = buckets.iter().map(|(_, c)| c).max().unwrap_or(&0);
-        let (first_ts, _) = buckets.first().unwrap();
-        let (last_ts, _) = buckets.last().unwrap();
+        let all_values = || {
+            lines
+                .iter()
+                .flat_map(|line| line.values.iter().flatten().copied())
+        };
+        let max_value = all_values().fold(0.0, f64::max);
+        let min_value = all_values().fold(0.0, f64::min);
+        let padding = ((max_value - min_value) / 10.0).max(1.0);
+        let first_ts = starts.first().unwrap();
+        // Extend the axis to the end of the last bucket so variable-length
+        // (calendar) buckets keep their true width
+        let last_ts = bucket_size.bucket_end(*starts.last().unwrap(), tz);
+        let label_format = if last_ts - *first_ts > chrono::Duration::days(2) {
+            "%Y-%m-%d"
+        } else {
+            "%H:%M"
+        };
 
         let mut chart = ChartBuilder::on(&root)
             .caption("Log Matches Over Time", ("sans-serif", 50).into_font())
@@ -186,37 +409,42 @@ pub fn plot_png(buckets: &[(DateTime<Utc>, usize)], output_file: &str) -> Result
             .y_label_area_size(60)
             .build_cartesian_2d(
                 first_ts.timestamp()..last_ts.timestamp(),
-                0..*max_count + (max_count / 10).max(1),
+                min_value..max_value + padding,
             )?;
 
         chart
             .configure_mesh()
-            .x_desc("Time")
-            .y_desc("Count")
+            .x_desc(format!("Time ({})", tz.name()))
+            .y_desc(y_label)
+            .y_label_formatter(&|y| format_value(*y))
             .x_label_formatter(&|x| {
                 DateTime::from_timestamp(*x, 0)
-                    .map(|dt| dt.format("%H:%M").to_string())
+                    .map(|dt| dt.with_timezone(tz).format(label_format).to_string())
                     .unwrap_or_default()
             })
             .axis_desc_style(("sans-serif", 20))
             .label_style(("sans-serif", 15))
             .draw()?;
 
-        // Draw line chart
-        chart
-            .draw_series(LineSeries::new(
-                buckets.iter().map(|(ts, count)| (ts.timestamp(), *count)),
-                &BLUE.mix(0.8),
-            ))?
-            .label("Matches")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
-
-        // Draw points
-        chart.draw_series(
-            buckets
-                .iter()
-                .map(|(ts, count)| Circle::new((ts.timestamp(), *count), 4, BLUE.filled())),
-        )?;
+        for (index, line) in lines.iter().enumerate() {
+            let (r, g, b) = series_color(index);
+            let color = RGBColor(r, g, b);
+            let points = || {
+                starts
+                    .iter()
+                    .zip(&line.values)
+                    .filter_map(|(ts, value)| value.map(|value| (ts.timestamp(), value)))
+            };
+
+            // Draw line chart
+            chart
+                .draw_series(LineSeries::new(points(), &color.mix(0.8)))?
+                .label(line.label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+
+            // Draw points
+            chart.draw_series(points().map(|point| Circle::new(point, 4, color.filled())))?;
+        }
 
         chart
             .configure_series_labels()
@@ -1,45 +1,299 @@
-use crate::bucket::TimeBucket;
+use crate::aggregate::AggFn;
+use crate::bucket::{
+    aggregate_totals, aggregate_values, rank_series, regroup_series, stack_series, stack_values,
+    AggregateRow, SeriesRow, TimeBucket,
+};
 use crate::cli::{Args, OutputFormat};
-use crate::output::{output_csv, output_json, output_table};
-use crate::plot::{plot_ascii, plot_png};
-use crate::reader::{create_readers, LogReader};
-use crate::timestamp::TimestampParser;
+use crate::filter::{Expr, LineFilter, PatternMatches};
+use crate::follow::{is_compressed, ChangeWatcher, FileTail, Reopen, POLL_INTERVAL};
+use crate::output::{
+    output_csv, output_grouped_json, output_json, output_series_csv, output_series_json,
+    output_series_table, output_table, output_value_csv, output_value_json, output_value_table,
+    stream_csv_header, stream_csv_row, stream_json_row,
+};
+use crate::plot::{
+    plot_ascii, plot_ascii_series, plot_ascii_values, plot_png, plot_png_series, plot_png_values,
+};
+use crate::range::{parse_time_expr, parse_window, TimeRange};
+use crate::reader::{
+    create_readers, expand_follow_files, has_dynamic_files, join_records, split_into_chunks,
+    LogReader,
+};
+use crate::structured::{Predicate, Record, DEFAULT_TS_FIELDS};
+use crate::timestamp::{DetectedFormat, ParseContext, TimestampParser};
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rayon::prelude::*;
 use regex::Regex;
-use std::thread;
-use std::time::Duration as StdDuration;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration as StdDuration, Instant};
+
+/// Uncompressed files at least twice this size are split into chunks so they
+/// can be processed by several workers
+const MIN_CHUNK_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Environment variable overriding `MIN_CHUNK_SIZE`, so tests can split small
+/// files without a user-facing option
+const MIN_CHUNK_SIZE_ENV: &str = "LOGPILE_MIN_CHUNK_SIZE";
+
+/// Matching lines without a timestamp tolerated at the start of a source
+/// before it is skipped
+const TIMESTAMP_PROBE_LINES: usize = 10;
+
+/// Consecutive timestamps past `--until`, in a source whose timestamps have
+/// been in order so far, after which the rest of the source is skipped
+const UNTIL_EXIT_LINES: usize = 10;
+
+/// Shortest time between refreshes of the live view in follow mode
+const FOLLOW_DISPLAY_INTERVAL: StdDuration = StdDuration::from_secs(1);
+
+/// How long past its end a bucket stays open for `--stream` when no later
+/// event closes it, so lines written just before the boundary still count
+const STREAM_CLOSE_DELAY: Duration = Duration::seconds(2);
+
+/// `--group-by` key for matching lines where the capture group took no part
+const NO_GROUP_KEY: &str = "(none)";
+
+/// Series a matching line counts towards
+enum LineSeries {
+    /// Indices of fixed series: the single series, or one per pattern
+    Fixed(Vec<usize>),
+    /// A `--group-by` key, resolved to a series when the line is counted
+    Key(String),
+}
+
+impl LineSeries {
+    fn indices(self, bucket: &mut TimeBucket) -> Vec<usize> {
+        match self {
+            LineSeries::Fixed(indices) => indices,
+            LineSeries::Key(key) => vec![bucket.series_index(&key)],
+        }
+    }
+
+    /// Count a line towards its series, aggregating its `--value` if it has one
+    fn add(self, bucket: &mut TimeBucket, timestamp: DateTime<Utc>, value: Option<f64>) {
+        for index in self.indices(bucket) {
+            match value {
+                Some(value) => bucket.add_value(timestamp, index, value),
+                None => bucket.add_to_series(timestamp, index),
+            }
+        }
+        bucket.add_line(timestamp);
+    }
+}
+
+/// A line that matched: the series it counts towards, the patterns it
+/// matched when they are needed, and, for structured input, its parsed fields
+struct LineMatch {
+    series: LineSeries,
+    patterns: Option<PatternMatches>,
+    record: Option<Record>,
+}
+
+/// Input read in follow mode: stdin or one file
+struct FollowSource {
+    /// File path, None for stdin
+    path: Option<String>,
+    /// Multiline record still being assembled
+    pending_record: Option<String>,
+    /// Timestamp format and year learned from the lines read so far; years
+    /// are inferred relative to now, as the source keeps growing
+    timestamp: ParseContext,
+}
+
+impl FollowSource {
+    fn new(path: Option<String>) -> Self {
+        Self {
+            path,
+            pending_record: None,
+            timestamp: ParseContext::default(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        self.path.as_deref().unwrap_or("<stdin>")
+    }
+}
+
+/// A file being followed
+struct FollowedFile {
+    tail: FileTail,
+    source: FollowSource,
+}
+
+/// Progress of `--stream` output in follow mode
+#[derive(Default)]
+struct StreamState {
+    /// Buckets starting before this have closed and been emitted
+    closed_until: Option<DateTime<Utc>>,
+    /// Counts last emitted for each open bucket with `--provisional`, so an
+    /// unchanged bucket is not repeated
+    provisional: BTreeMap<DateTime<Utc>, Vec<usize>>,
+}
+
+/// Counts and diagnostics from scanning one file or one chunk of a file
+struct ScanResult {
+    bucket: TimeBucket,
+    lines_processed: usize,
+    matching_lines: usize,
+    /// Matching lines seen before the first parsed timestamp
+    leading_unparsed: usize,
+    timestamp_found: bool,
+    first_timestamp_failure: Option<String>,
+    /// Verbose warnings, printed in order once the file is merged
+    warnings: Vec<String>,
+    /// Matching lines per pattern, counted with `--verbose`
+    pattern_matches: Vec<usize>,
+    /// Timestamp format learned from the lines scanned
+    timestamp_format: Option<DetectedFormat>,
+}
+
+impl ScanResult {
+    fn new(bucket: TimeBucket, patterns: usize) -> Self {
+        Self {
+            bucket,
+            lines_processed: 0,
+            matching_lines: 0,
+            leading_unparsed: 0,
+            timestamp_found: false,
+            first_timestamp_failure: None,
+            warnings: Vec::new(),
+            pattern_matches: vec![0; patterns],
+            timestamp_format: None,
+        }
+    }
+}
 
 pub struct LogProcessor {
     args: Args,
     patterns: Vec<Regex>,
+    /// The patterns combined by `--all` or `--expr`, and `--exclude`
+    filter: LineFilter,
+    /// `--value` pattern extracting the number to aggregate
+    value_pattern: Option<Regex>,
+    /// `--where` conditions on structured fields
+    predicates: Vec<Predicate>,
+    /// Whether `--group-by` names a structured field rather than a capture group
+    group_by_field: bool,
+    /// `--record-start` pattern marking the first line of a multiline record
+    record_start: Option<Regex>,
     timestamp_parser: TimestampParser,
     bucket: TimeBucket,
+    range: TimeRange,
+    /// `--window` length of the sliding window in follow mode
+    window: Option<Duration>,
+    /// End of the sliding window as of the latest refresh
+    window_end: Option<DateTime<Utc>>,
+    stream: StreamState,
+    /// Timestamp format detected in each source, for `--verbose` and JSON
+    timestamp_formats: Vec<(String, String)>,
 }
 
 impl LogProcessor {
     pub fn new(args: Args) -> Result<Self> {
         args.validate()?;
 
+        // Primary pattern (respecting --no-default-pattern), then --grep patterns
         let mut patterns = Vec::new();
-
-        // Add primary pattern if provided (respecting --no-default-pattern)
-        if let Some(pattern) = args.get_pattern() {
-            patterns.push(Regex::new(pattern)?);
+        let mut series = Vec::new();
+        let expr = match args.get_pattern().filter(|_| args.expr) {
+            Some(expression) => {
+                let (expr, terms) = Expr::parse(expression)?;
+                for term in terms {
+                    patterns.push(Regex::new(&term)?);
+                }
+                Some(expr)
+            }
+            None => {
+                for (name, pattern) in args.labelled_patterns() {
+                    if args.series && series.contains(&name) {
+                        anyhow::bail!("Duplicate series name '{}'", name);
+                    }
+                    patterns.push(Regex::new(&pattern)?);
+                    series.push(name);
+                }
+                // No patterns means match everything (--no-default-pattern)
+                let indices: Vec<Expr> = (0..patterns.len()).map(Expr::Pattern).collect();
+                match indices.len() {
+                    0 => None,
+                    _ if args.all => Some(Expr::And(indices)),
+                    _ => Some(Expr::Or(indices)),
+                }
+            }
+        };
+        let sources: Vec<&str> = patterns.iter().map(Regex::as_str).collect();
+        let exclude: Vec<&str> = args.exclude.iter().map(String::as_str).collect();
+        let filter = LineFilter::new(&sources, expr, &exclude)?;
+        if args.series && patterns.is_empty() {
+            anyhow::bail!("--series needs at least one pattern");
         }
-
-        // Add additional grep patterns
-        for pattern in &args.grep {
-            patterns.push(Regex::new(pattern)?);
+        let mut group_by_field = false;
+        if let Some(ref group) = args.group_by {
+            let has_group = patterns
+                .iter()
+                .any(|p| p.capture_names().flatten().any(|name| name == group));
+            group_by_field = !has_group && args.structured_format().is_some();
+            if !has_group && !group_by_field {
+                anyhow::bail!(
+                    "Capture group '{}' not found in any pattern; name it with (?P<{}>...)",
+                    group,
+                    group
+                );
+            }
         }
 
-        let timestamp_parser = TimestampParser::new(args.time_format.clone());
-        let bucket = TimeBucket::new(args.bucket.clone())?;
+        let value_pattern = args.value.as_deref().map(Regex::new).transpose()?;
+        let record_start = args.record_start.as_deref().map(Regex::new).transpose()?;
+        let predicates = args
+            .where_fields
+            .iter()
+            .map(|condition| condition.parse())
+            .collect::<Result<Vec<Predicate>>>()?;
+
+        let input_tz = args.input_tz()?;
+        let mut timestamp_parser =
+            TimestampParser::new(args.time_format.clone()).with_timezone(input_tz, args.dst_policy);
+        if let Some(ref reference) = args.reference_date {
+            let reference = parse_time_expr(reference, Utc::now(), &input_tz)
+                .map_err(|e| anyhow::anyhow!("--reference-date: {}", e))?;
+            timestamp_parser = timestamp_parser.with_reference_date(reference);
+        }
+        // Structured records look the field up themselves
+        if let (Some(field), None) = (&args.ts_field, args.structured_format()) {
+            timestamp_parser = timestamp_parser.with_field(field);
+        }
+        let display_tz = args.display_tz()?;
+        let mut bucket = TimeBucket::new(args.bucket.clone())?.with_timezone(display_tz);
+        if args.series {
+            bucket = bucket.with_series(series);
+        }
+        if let Some(agg) = args.aggregation() {
+            bucket = bucket.with_aggregation(agg);
+        }
+        let range = TimeRange::parse(
+            args.since.as_deref(),
+            args.until.as_deref(),
+            Utc::now(),
+            &input_tz,
+        )?;
+        let window = args.window.as_deref().map(parse_window).transpose()?;
 
         Ok(Self {
             args,
             patterns,
+            filter,
+            value_pattern,
+            predicates,
+            group_by_field,
+            record_start,
             timestamp_parser,
             bucket,
+            range,
+            window,
+            window_end: None,
+            stream: StreamState::default(),
+            timestamp_formats: Vec::new(),
         })
     }
 
@@ -54,88 +308,110 @@ impl LogProcessor {
     fn run_batch_mode(&mut self) -> Result<()> {
         let files = self.args.get_files();
         let readers = create_readers(&files)?;
+        let jobs = self.args.jobs();
+        let min_chunk_size = std::env::var(MIN_CHUNK_SIZE_ENV)
+            .ok()
+            .and_then(|size| size.parse().ok())
+            .filter(|&size| size > 0)
+            .unwrap_or(MIN_CHUNK_SIZE);
+
+        // Large uncompressed files are split at line boundaries so a single
+        // file can be spread across several workers. Multiline records could
+        // straddle a chunk boundary, so those files are read whole.
+        let mut sources = Vec::new();
+        let mut work = Vec::new();
+        for (index, (source, reader)) in readers.into_iter().enumerate() {
+            match (&source, &reader) {
+                (Some(path), LogReader::PlainFile(_)) if jobs > 1 && !self.args.multiline() => {
+                    let ranges = split_into_chunks(path, jobs, min_chunk_size)?;
+                    if ranges.len() > 1 {
+                        for (start, end) in ranges {
+                            work.push((index, LogReader::open_range(path, start, end)?));
+                        }
+                    } else {
+                        work.push((index, reader));
+                    }
+                }
+                _ => work.push((index, reader)),
+            }
+            sources.push(source);
+        }
+
+        // Workers fill their own buckets; results are merged below in file and
+        // chunk order so the output matches a sequential run
+        let results: Box<dyn Iterator<Item = (usize, Result<ScanResult>)>> =
+            if jobs > 1 && work.len() > 1 {
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+                let this = &*self;
+                let results: Vec<_> = pool.install(|| {
+                    work.into_par_iter()
+                        .map(|(index, mut reader)| {
+                            let source = sources[index].as_deref();
+                            (index, this.scan_lines(source, reader.lines()))
+                        })
+                        .collect()
+                });
+                Box::new(results.into_iter())
+            } else {
+                let this = &*self;
+                let sources = &sources;
+                Box::new(work.into_iter().map(move |(index, mut reader)| {
+                    let source = sources[index].as_deref();
+                    (index, this.scan_lines(source, reader.lines()))
+                }))
+            };
+        let mut results = results.peekable();
+
+        let mut merged = self.bucket.empty_like();
+        let mut pattern_matches = vec![0; self.patterns.len()];
+        let mut timestamp_formats = Vec::new();
         let mut total_files_processed = 0;
         let mut files_with_matches = 0;
 
-        for (source, mut reader) in readers {
+        for (index, source) in sources.iter().enumerate() {
             if let Some(ref src) = source {
                 if self.args.verbose {
                     eprintln!("Processing: {}", src);
                 }
             }
 
-            total_files_processed += 1;
-            let mut lines_processed = 0;
-            let mut matching_lines_processed = 0;
-            let mut timestamp_found = false;
-            let mut first_timestamp_failure = None;
-            let mut first_matching_line = None;
-
-            for line_result in reader.lines() {
-                let line = line_result?;
-                lines_processed += 1;
-
-                if self.matches_patterns(&line) {
-                    matching_lines_processed += 1;
-
-                    // Track the first matching line for early exit
-                    if first_matching_line.is_none() {
-                        first_matching_line = Some(line.clone());
-                    }
+            let mut chunks = Vec::new();
+            while let Some((_, result)) = results.next_if(|(i, _)| *i == index) {
+                chunks.push(result?);
+            }
 
-                    // Try to extract timestamp
-                    if let Some(timestamp) = self.timestamp_parser.parse_line(&line) {
-                        self.bucket.add(timestamp);
-                        timestamp_found = true;
-                    } else {
-                        // Track the first timestamp failure for early exit
-                        if first_timestamp_failure.is_none() {
-                            first_timestamp_failure = Some(line.clone());
-                        }
+            total_files_processed += 1;
+            let (file, probe_failed) = self.combine_chunks(chunks);
 
-                        // If we've processed more than 10 matching lines and still no timestamp found,
-                        // and we're not in a custom format mode, fail early
-                        if matching_lines_processed > 10
-                            && !timestamp_found
-                            && self.args.time_format.is_none()
-                        {
-                            if self.args.fail_quick {
-                                eprintln!(
-                                    "Error: No valid timestamps found in first {} matching lines. First failure: {}",
-                                    matching_lines_processed,
-                                    &first_timestamp_failure.unwrap().chars().take(80).collect::<String>()
-                                );
-                                eprintln!("Use --time-format to specify a custom timestamp format, or check if your log file has timestamps.");
-                                anyhow::bail!("No valid timestamps detected in log file");
-                            } else {
-                                if self.args.verbose {
-                                    eprintln!(
-                                        "Warning: No valid timestamps found in first {} matching lines in {}",
-                                        matching_lines_processed,
-                                        source.as_ref().unwrap_or(&"<stdin>".to_string())
-                                    );
-                                }
-                                // Continue to next file instead of failing
-                                break;
-                            }
-                        }
+            for warning in &file.warnings {
+                eprintln!("{}", warning);
+            }
 
-                        if self.args.verbose {
-                            eprintln!(
-                                "Warning: Could not parse timestamp from: {}",
-                                &line.chars().take(80).collect::<String>()
-                            );
-                        }
-                    }
+            // The first matching lines had no timestamps: skip the file
+            if probe_failed {
+                if self.args.fail_quick {
+                    eprintln!(
+                        "Error: No valid timestamps found in first {} matching lines. First failure: {}",
+                        file.matching_lines,
+                        &file.first_timestamp_failure.as_deref().unwrap_or("").chars().take(80).collect::<String>()
+                    );
+                    eprintln!("Use --time-format to specify a custom timestamp format, or check if your log file has timestamps.");
+                    anyhow::bail!("No valid timestamps detected in log file");
+                } else if self.args.verbose {
+                    eprintln!(
+                        "Warning: No valid timestamps found in first {} matching lines in {}",
+                        file.matching_lines,
+                        source.as_ref().unwrap_or(&"<stdin>".to_string())
+                    );
                 }
             }
 
             // Handle files with no matching lines
-            if lines_processed > 0 && matching_lines_processed == 0 {
+            if file.lines_processed > 0 && file.matching_lines == 0 {
                 if self.args.fail_quick {
                     eprintln!(
                         "No lines matched the search pattern in {} lines processed",
-                        lines_processed
+                        file.lines_processed
                     );
                     eprintln!("Try a different search pattern or check if your log file contains the expected content.");
                     anyhow::bail!("No matching lines found in log file");
@@ -144,7 +420,7 @@ impl LogProcessor {
                         eprintln!(
                             "No lines matched the search pattern in {} ({} lines processed)",
                             source.as_ref().unwrap_or(&"<stdin>".to_string()),
-                            lines_processed
+                            file.lines_processed
                         );
                     }
                     // Continue to next file instead of failing
@@ -153,11 +429,11 @@ impl LogProcessor {
             }
 
             // Handle files with matching lines but no timestamps
-            if matching_lines_processed > 0 && !timestamp_found && self.args.time_format.is_none() {
+            if file.matching_lines > 0 && !file.timestamp_found && self.args.time_format.is_none() {
                 if self.args.fail_quick {
                     eprintln!(
                         "Error: No valid timestamps found in {} matching lines",
-                        matching_lines_processed
+                        file.matching_lines
                     );
                     eprintln!("Use --time-format to specify a custom timestamp format, or check if your log file has timestamps.");
                     anyhow::bail!("No valid timestamps detected in log file");
@@ -165,7 +441,7 @@ impl LogProcessor {
                     if self.args.verbose {
                         eprintln!(
                             "Warning: No valid timestamps found in {} matching lines in {}",
-                            matching_lines_processed,
+                            file.matching_lines,
                             source.as_ref().unwrap_or(&"<stdin>".to_string())
                         );
                     }
@@ -175,9 +451,31 @@ impl LogProcessor {
             }
 
             // Track files that had matches
-            if matching_lines_processed > 0 {
+            if file.matching_lines > 0 {
                 files_with_matches += 1;
             }
+
+            if !probe_failed {
+                merged.merge(file.bucket);
+                add_counts(&mut pattern_matches, &file.pattern_matches);
+            }
+            if let Some(format) = file.timestamp_format {
+                if self.args.verbose {
+                    eprintln!("Timestamp format: {}", format);
+                }
+                let name = source.as_deref().unwrap_or("<stdin>");
+                timestamp_formats.push((name.to_string(), format.to_string()));
+            }
+        }
+        drop(results);
+        self.bucket.merge(merged);
+        self.timestamp_formats = timestamp_formats;
+
+        if self.args.verbose && self.patterns.len() > 1 {
+            eprintln!("Matching lines per pattern:");
+            for (pattern, count) in self.patterns.iter().zip(&pattern_matches) {
+                eprintln!("  {}: {}", pattern.as_str(), count);
+            }
         }
 
         // Check if any files had matches
@@ -196,202 +494,866 @@ impl LogProcessor {
     }
 
     fn run_follow_mode(&mut self) -> Result<()> {
+        if self.args.stream
+            && self.args.output_format() == OutputFormat::Csv
+            && !self.args.no_headers
+        {
+            let columns = if self.args.series {
+                self.bucket.series_names().to_vec()
+            } else {
+                vec!["count".to_string()]
+            };
+            stream_csv_header(&columns, self.args.provisional)?;
+        }
+
         if self.args.files.is_empty() {
             // Follow mode with stdin
             eprintln!("Following: stdin (press Ctrl+C to stop)");
             self.run_follow_stdin()
-        } else if self.args.files.len() > 1 {
-            anyhow::bail!("Follow mode only supports a single file");
         } else {
-            // Follow mode with file
-            let file_path = self.args.files[0].clone();
-            eprintln!("Following: {} (press Ctrl+C to stop)", file_path);
-            self.run_follow_file(&file_path)
+            eprintln!(
+                "Following: {} (press Ctrl+C to stop)",
+                self.args.files.join(", ")
+            );
+            self.run_follow_files()
         }
     }
 
     fn run_follow_stdin(&mut self) -> Result<()> {
         use std::io::{self, BufRead};
-        use std::time::{Duration, Instant};
 
-        let stdin = io::stdin();
-        let handle = stdin.lock();
-        let mut last_display = Instant::now();
-        let display_interval = Duration::from_secs(1);
+        // Lines are read on their own thread so that --stream can close
+        // buckets on time while stdin is idle
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
 
-        for line_result in handle.lines() {
-            let line = line_result?;
-            if self.matches_patterns(&line) {
-                if let Some(timestamp) = self.timestamp_parser.parse_line(&line) {
-                    self.bucket.add(timestamp);
-                } else if self.args.verbose {
-                    eprintln!(
-                        "Warning: Could not parse timestamp from: {}",
-                        &line.chars().take(80).collect::<String>()
-                    );
+        let mut source = FollowSource::new(None);
+        let mut pending = false;
+        let mut last_display = Instant::now();
+        loop {
+            let timeout = FOLLOW_DISPLAY_INTERVAL.saturating_sub(last_display.elapsed());
+            match lines.recv_timeout(timeout) {
+                Ok(line) => {
+                    self.record_follow_line(&mut source, &line?);
+                    pending = true;
                 }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
             }
 
             // Only refresh display every 1 second
-            if last_display.elapsed() >= display_interval {
+            if (pending || self.args.stream) && last_display.elapsed() >= FOLLOW_DISPLAY_INTERVAL {
                 self.display_follow_results()?;
+                pending = false;
                 last_display = Instant::now();
             }
         }
 
-        // Final display
-        self.display_follow_results()?;
-        Ok(())
+        // Final display; every bucket is complete at end of input
+        self.flush_follow_record(&mut source);
+        if self.args.stream {
+            self.stream_follow_results(true)
+        } else {
+            self.display_follow_results()
+        }
     }
 
-    fn run_follow_file(&mut self, file_path: &str) -> Result<()> {
-        // Track file position to only read new lines
-        let mut last_position = 0u64;
+    fn run_follow_files(&mut self) -> Result<()> {
+        let mut files = Vec::new();
+        let mut known = Vec::new();
+        let mut watcher = ChangeWatcher::new(&[]);
+        self.add_follow_files(&mut files, &mut known, &mut watcher, true)?;
 
-        // Initial read
-        let mut reader = LogReader::new(Some(file_path))?;
-        let lines = reader.lines();
-
-        for line_result in lines {
-            let line = line_result?;
-            last_position += 1;
-            if self.matches_patterns(&line) {
-                if let Some(timestamp) = self.timestamp_parser.parse_line(&line) {
-                    self.bucket.add(timestamp);
-                } else if self.args.verbose {
-                    eprintln!(
-                        "Warning: Could not parse timestamp from: {}",
-                        &line.chars().take(80).collect::<String>()
-                    );
-                }
+        // Globs and directories are expanded again to pick up new files
+        let rescan = has_dynamic_files(&self.args.files);
+        if files.is_empty() && !rescan {
+            anyhow::bail!("No files to follow");
+        }
+        if self.args.verbose {
+            if watcher.is_polling() {
+                eprintln!(
+                    "File notifications unavailable, polling every {}s",
+                    POLL_INTERVAL.as_secs()
+                );
+            } else {
+                eprintln!("Watching for changes with file notifications");
             }
         }
 
-        // Show initial results
+        // Initial read of the existing content
+        for file in &mut files {
+            self.read_follow_file(file)?;
+        }
         self.display_follow_results()?;
 
-        // For a real tail -f implementation, we'd need to use inotify or similar
-        // For simplicity, we'll poll the file
+        // Only the bytes past the stored offsets are read on each change, and
+        // the display is refreshed at most once per interval
+        let mut pending = false;
+        let mut last_display = Instant::now();
         loop {
-            thread::sleep(StdDuration::from_secs(1));
+            // --stream refreshes while idle too, to close buckets on time
+            let mut timeout = if pending || self.args.stream {
+                FOLLOW_DISPLAY_INTERVAL.saturating_sub(last_display.elapsed())
+            } else {
+                watcher.idle_timeout()
+            };
+            if rescan {
+                timeout = timeout.min(POLL_INTERVAL);
+            }
+            watcher.wait(timeout);
 
-            let mut reader = LogReader::new(Some(file_path))?;
-            let mut lines = reader.lines();
+            if rescan {
+                let first_new = files.len();
+                self.add_follow_files(&mut files, &mut known, &mut watcher, false)?;
+                for file in &files[first_new..] {
+                    eprintln!("Following new file: {}", file.tail.path().display());
+                }
+            }
+            for file in &mut files {
+                if self.read_follow_file(file)? {
+                    pending = true;
+                }
+            }
 
-            // Skip to the last position we read
-            for _ in 0..last_position {
-                if lines.next().is_none() {
-                    break;
+            if (pending || self.args.stream) && last_display.elapsed() >= FOLLOW_DISPLAY_INTERVAL {
+                self.display_follow_results()?;
+                pending = false;
+                last_display = Instant::now();
+            }
+        }
+    }
+
+    /// Start following the files FILES expands to that are not yet `known`.
+    /// Compressed files are skipped with a warning. Other files that cannot
+    /// be opened are an error at startup, and retried later otherwise, as
+    /// they may have vanished between the glob and the open.
+    fn add_follow_files(
+        &self,
+        files: &mut Vec<FollowedFile>,
+        known: &mut Vec<String>,
+        watcher: &mut ChangeWatcher,
+        startup: bool,
+    ) -> Result<()> {
+        for path in expand_follow_files(&self.args.files)? {
+            if known.contains(&path) {
+                continue;
+            }
+
+            let opened = match is_compressed(&path) {
+                Ok(true) => {
+                    eprintln!("Warning: Skipping compressed file: {}", path);
+                    known.push(path);
+                    continue;
                 }
+                Ok(false) => FileTail::open(&path),
+                Err(e) => Err(e),
+            };
+            match opened {
+                Ok(tail) => {
+                    // Without a notification for this file, it is still read
+                    // whenever the watcher times out
+                    let _ = watcher.add(tail.path());
+                    files.push(FollowedFile {
+                        tail,
+                        source: FollowSource::new(Some(path.clone())),
+                    });
+                }
+                Err(e) if startup => return Err(e),
+                Err(_) => continue,
             }
+            known.push(path);
+        }
+        Ok(())
+    }
 
-            let mut new_lines_found = false;
-            for line_result in lines {
-                let line = line_result?;
-                last_position += 1;
-                new_lines_found = true;
+    /// Count the lines appended to a followed file; true if there were any.
+    /// They are read a bounded batch at a time, so a large file is caught
+    /// up on without holding all of it.
+    fn read_follow_file(&mut self, file: &mut FollowedFile) -> Result<bool> {
+        let mut any = false;
+        loop {
+            let read = file.tail.read_lines()?;
+            if self.args.verbose {
+                match read.reopened {
+                    Some(Reopen::Rotated) => eprintln!(
+                        "{} was rotated, following the new file",
+                        file.tail.path().display()
+                    ),
+                    Some(Reopen::Truncated) => eprintln!(
+                        "{} was truncated, reading from the start",
+                        file.tail.path().display()
+                    ),
+                    None => {}
+                }
+            }
+            if read.lines.is_empty() {
+                return Ok(any);
+            }
 
-                if self.matches_patterns(&line) {
-                    if let Some(timestamp) = self.timestamp_parser.parse_line(&line) {
-                        self.bucket.add(timestamp);
-                    } else if self.args.verbose {
-                        eprintln!(
-                            "Warning: Could not parse timestamp from: {}",
-                            &line.chars().take(80).collect::<String>()
-                        );
+            for line in &read.lines {
+                self.record_follow_line(&mut file.source, line);
+            }
+            any = true;
+        }
+    }
+
+    fn display_follow_results(&mut self) -> Result<()> {
+        // Buckets that have slid out of the window are dropped for good
+        if let Some(window) = self.window {
+            let now = Utc::now();
+            self.window_end = Some(now);
+            self.bucket.evict_before(now - window);
+        }
+
+        if self.args.stream {
+            return self.stream_follow_results(false);
+        }
+
+        // PNG in follow mode doesn't make much sense, but handle it
+        if self.args.output_format() != OutputFormat::Png {
+            print!("\x1B[2J\x1B[1;1H"); // Clear screen
+        }
+        let _ = self.output_results();
+        Ok(())
+    }
+
+    /// Append the buckets that closed since the last refresh for `--stream`,
+    /// and with `--provisional` the open buckets whose counts changed. A
+    /// bucket closes once a later event has been counted or the clock has
+    /// passed its end; at end of input every bucket is closed. Events for a
+    /// bucket that was already emitted are not reported.
+    fn stream_follow_results(&mut self, finished: bool) -> Result<()> {
+        let size = self.bucket.bucket_size();
+        let tz = self.bucket.timezone();
+
+        // Start of the earliest bucket still open
+        let open_from = (!finished).then(|| {
+            let now = Utc::now() - STREAM_CLOSE_DELAY;
+            let latest = self
+                .bucket
+                .time_range()
+                .map_or(now, |(_, last)| last.max(now));
+            size.bucket_start(latest, &tz)
+        });
+
+        let rows = if self.args.fill() {
+            let range = self.display_range();
+            let until = match (open_from, range.until) {
+                (Some(open), Some(until)) => Some(open.min(until)),
+                (open, until) => open.or(until),
+            };
+            self.bucket.get_filled_series_buckets(range.since, until)?
+        } else {
+            self.bucket.get_series_buckets()
+        };
+
+        for (start, counts) in rows {
+            if self
+                .stream
+                .closed_until
+                .is_some_and(|closed| start < closed)
+            {
+                continue;
+            }
+            if open_from.is_none_or(|open| start < open) {
+                self.stream_row(start, &counts, self.args.provisional.then_some(false))?;
+            } else if self.args.provisional && self.stream.provisional.get(&start) != Some(&counts)
+            {
+                self.stream_row(start, &counts, Some(true))?;
+                self.stream.provisional.insert(start, counts);
+            }
+        }
+
+        if let Some(open) = open_from {
+            self.stream.closed_until = self.stream.closed_until.max(Some(open));
+            self.stream.provisional = self.stream.provisional.split_off(&open);
+        }
+        Ok(())
+    }
+
+    /// Append one bucket to the `--stream` output
+    fn stream_row(
+        &self,
+        start: DateTime<Utc>,
+        counts: &[usize],
+        provisional: Option<bool>,
+    ) -> Result<()> {
+        let tz = self.bucket.timezone();
+        if self.args.output_format() == OutputFormat::Csv {
+            return stream_csv_row(start, counts, &tz, provisional);
+        }
+
+        let names = self.bucket.series_names();
+        let breakdown = if self.args.group_by.is_some() {
+            Some(("groups", names))
+        } else if self.args.series || self.args.per_file {
+            Some(("series", names))
+        } else {
+            None
+        };
+        stream_json_row(
+            start,
+            counts,
+            breakdown,
+            &self.bucket.bucket_size(),
+            &tz,
+            provisional,
+        )
+    }
+
+    /// Feed one line read in follow mode. In multiline mode lines are
+    /// assembled into records per source, and a record is counted once the
+    /// next starts.
+    fn record_follow_line(&mut self, source: &mut FollowSource, line: &str) {
+        if !self.args.multiline() {
+            return self.count_follow_record(source, line);
+        }
+
+        if source.pending_record.is_none() || self.starts_record(line) {
+            if let Some(record) = source.pending_record.replace(line.to_string()) {
+                self.count_follow_record(source, &record);
+            }
+        } else if let Some(ref mut record) = source.pending_record {
+            record.push('\n');
+            record.push_str(line);
+        }
+    }
+
+    /// Count the multiline record still being assembled, at end of input
+    fn flush_follow_record(&mut self, source: &mut FollowSource) {
+        if let Some(record) = source.pending_record.take() {
+            self.count_follow_record(source, &record);
+        }
+    }
+
+    /// Match and bucket one record read in follow mode
+    fn count_follow_record(&mut self, source: &mut FollowSource, line: &str) {
+        let Some(matched) = self.match_line(source.path.as_deref(), line) else {
+            return;
+        };
+
+        let previous_format = source.timestamp.format;
+        let timestamp = self.line_timestamp(line, matched.record.as_ref(), &mut source.timestamp);
+        if let Some(format) = source
+            .timestamp
+            .format
+            .filter(|f| Some(*f) != previous_format)
+        {
+            self.set_timestamp_format(source.name(), format);
+        }
+
+        if let Some(timestamp) = timestamp {
+            if self.range.contains(timestamp) && self.in_window(timestamp) {
+                let value = self.extract_value(line);
+                matched.series.add(&mut self.bucket, timestamp, value);
+            }
+        } else if self.args.verbose {
+            eprintln!(
+                "Warning: Could not parse timestamp from: {}",
+                &line.chars().take(80).collect::<String>()
+            );
+        }
+    }
+
+    /// Record the timestamp format detected in a followed source
+    fn set_timestamp_format(&mut self, source: &str, format: DetectedFormat) {
+        if self.args.verbose {
+            eprintln!("Timestamp format for {}: {}", source, format);
+        }
+        let format = format.to_string();
+        match self
+            .timestamp_formats
+            .iter_mut()
+            .find(|(name, _)| name == source)
+        {
+            Some(entry) => entry.1 = format,
+            None => self.timestamp_formats.push((source.to_string(), format)),
+        }
+    }
+
+    /// Match, timestamp and bucket the lines of one file or file chunk
+    fn scan_lines(
+        &self,
+        source: Option<&str>,
+        lines: impl Iterator<Item = Result<String>>,
+    ) -> Result<ScanResult> {
+        let mut result = ScanResult::new(self.bucket.empty_like(), self.patterns.len());
+        let mut context = ParseContext::new(source.and_then(modified_time));
+        let mut previous_timestamp = None;
+        let mut in_order = true;
+        let mut past_until = 0;
+
+        for line_result in join_records(lines, |line| self.starts_record(line)) {
+            let line = line_result?;
+            result.lines_processed += 1;
+
+            let Some(matched) = self.match_line(source, &line) else {
+                continue;
+            };
+            result.matching_lines += 1;
+            for index in matched.patterns.iter().flat_map(PatternMatches::iter) {
+                result.pattern_matches[index] += 1;
+            }
+
+            let timestamp = self.line_timestamp(&line, matched.record.as_ref(), &mut context);
+            if let Some(timestamp) = timestamp {
+                result.timestamp_found = true;
+                in_order &= previous_timestamp.is_none_or(|previous| timestamp >= previous);
+                previous_timestamp = Some(timestamp);
+
+                if self.range.contains(timestamp) {
+                    let value = self.extract_value(&line);
+                    matched.series.add(&mut result.bucket, timestamp, value);
+                }
+
+                // Time-ordered input that has moved past --until has nothing
+                // left to count
+                if in_order && self.range.is_past(timestamp) {
+                    past_until += 1;
+                    if past_until >= UNTIL_EXIT_LINES {
+                        if self.args.verbose {
+                            result.warnings.push(
+                                "Skipping the rest of the input: timestamps are past --until"
+                                    .to_string(),
+                            );
+                        }
+                        break;
                     }
+                } else {
+                    past_until = 0;
                 }
+                continue;
             }
 
-            // Only update display if new lines were found
-            if new_lines_found {
-                self.display_follow_results()?;
+            if !result.timestamp_found {
+                result.leading_unparsed += 1;
+                if result.first_timestamp_failure.is_none() {
+                    result.first_timestamp_failure = Some(line.clone());
+                }
+
+                // If the first matching lines have no timestamp and we're not in
+                // a custom format mode, give up on this source early
+                if result.leading_unparsed > TIMESTAMP_PROBE_LINES
+                    && self.args.time_format.is_none()
+                {
+                    break;
+                }
+            }
+
+            if self.args.verbose {
+                result.warnings.push(format!(
+                    "Warning: Could not parse timestamp from: {}",
+                    &line.chars().take(80).collect::<String>()
+                ));
             }
         }
+
+        result.timestamp_format = context.format;
+        Ok(result)
     }
 
-    fn display_follow_results(&self) -> Result<()> {
-        let buckets = self.bucket.get_buckets();
+    /// Combine the chunk results of one file in order. Returns the combined
+    /// result and whether the file failed the leading timestamp probe, in which
+    /// case only what a sequential scan would have seen is kept.
+    fn combine_chunks(&self, chunks: Vec<ScanResult>) -> (ScanResult, bool) {
+        let mut file = ScanResult::new(self.bucket.empty_like(), self.patterns.len());
 
-        match self.args.output_format() {
-            OutputFormat::Table => {
-                print!("\x1B[2J\x1B[1;1H"); // Clear screen
-                let bucket_size = self.bucket.bucket_size_seconds();
-                let _ = output_table(&buckets, bucket_size);
-            }
-            OutputFormat::Csv => {
-                // For CSV in follow mode, we need to clear and rewrite
-                print!("\x1B[2J\x1B[1;1H"); // Clear screen
-                let _ = output_csv(&buckets, self.args.no_headers);
+        for chunk in chunks {
+            if !file.timestamp_found {
+                file.leading_unparsed += chunk.leading_unparsed;
+                if file.first_timestamp_failure.is_none() {
+                    file.first_timestamp_failure = chunk.first_timestamp_failure;
+                }
+
+                if file.leading_unparsed > TIMESTAMP_PROBE_LINES && self.args.time_format.is_none()
+                {
+                    let remaining = TIMESTAMP_PROBE_LINES.saturating_sub(file.warnings.len());
+                    file.warnings
+                        .extend(chunk.warnings.into_iter().take(remaining));
+                    file.lines_processed += chunk.lines_processed;
+                    file.matching_lines = TIMESTAMP_PROBE_LINES + 1;
+                    return (file, true);
+                }
             }
-            OutputFormat::Json => {
-                print!("\x1B[2J\x1B[1;1H"); // Clear screen
-                let bucket_size = self.bucket.bucket_size_seconds();
-                let time_range = self.bucket.time_range();
-                let _ = output_json(&buckets, bucket_size, time_range);
+
+            file.lines_processed += chunk.lines_processed;
+            file.matching_lines += chunk.matching_lines;
+            file.timestamp_found |= chunk.timestamp_found;
+            file.warnings.extend(chunk.warnings);
+            file.bucket.merge(chunk.bucket);
+            add_counts(&mut file.pattern_matches, &chunk.pattern_matches);
+            file.timestamp_format = file.timestamp_format.or(chunk.timestamp_format);
+        }
+
+        (file, false)
+    }
+
+    /// Whether a line begins a new record; always true outside multiline mode
+    fn starts_record(&self, line: &str) -> bool {
+        if !self.args.multiline() {
+            return true;
+        }
+        match self.record_start {
+            Some(ref pattern) => pattern.is_match(line),
+            None => self.timestamp_parser.starts_with_timestamp(line),
+        }
+    }
+
+    /// Match a line from `source` (None for stdin) against the patterns and,
+    /// for structured input, the `--where` conditions. Lines that are not
+    /// valid records never match.
+    fn match_line(&self, source: Option<&str>, line: &str) -> Option<LineMatch> {
+        let (mut series, patterns) = self.match_series(line)?;
+        if self.args.per_file {
+            series = LineSeries::Key(source.unwrap_or("<stdin>").to_string());
+        }
+        let Some(format) = self.args.structured_format() else {
+            return Some(LineMatch {
+                series,
+                patterns,
+                record: None,
+            });
+        };
+
+        let record = Record::parse(format, line)?;
+        if !self.predicates.iter().all(|p| p.matches(&record)) {
+            return None;
+        }
+        if let (true, Some(group)) = (self.group_by_field, &self.args.group_by) {
+            let key = record.field(group).map_or_else(
+                || NO_GROUP_KEY.to_string(),
+                |value| value.as_text().into_owned(),
+            );
+            series = LineSeries::Key(key);
+        }
+        Some(LineMatch {
+            series,
+            patterns,
+            record: Some(record),
+        })
+    }
+
+    /// Timestamp of a matching line: from `--ts-field` or a well-known field
+    /// for structured input, falling back to scanning the text when no
+    /// well-known field is present. `context` carries what the source's
+    /// previous lines taught the parser.
+    fn line_timestamp(
+        &self,
+        line: &str,
+        record: Option<&Record>,
+        context: &mut ParseContext,
+    ) -> Option<DateTime<Utc>> {
+        let parser = &self.timestamp_parser;
+        let Some(record) = record else {
+            return parser.parse_line_cached(line, context);
+        };
+
+        let field = match self.args.ts_field {
+            Some(ref path) => record.field(path),
+            None => match DEFAULT_TS_FIELDS.iter().find_map(|path| record.field(path)) {
+                Some(field) => Some(field),
+                None => return parser.parse_line_cached(line, context),
+            },
+        };
+        field.and_then(|value| parser.parse_field_cached(&value, context))
+    }
+
+    /// Series a line counts towards, or None if it does not match. The
+    /// patterns it matched come from the same pass over the line, and are
+    /// only collected when the series or `--verbose` statistics need them.
+    fn match_series(&self, line: &str) -> Option<(LineSeries, Option<PatternMatches>)> {
+        let per_pattern = self.args.verbose && self.patterns.len() > 1;
+        if !self.args.series && self.args.group_by.is_none() && !per_pattern {
+            let matched = self.filter.is_match(line);
+            return matched.then(|| (LineSeries::Fixed(vec![0]), None));
+        }
+
+        let matched = self.filter.matches(line)?;
+        let series = match self.args.group_by {
+            // The key comes from the first matching pattern that captures it
+            Some(ref group) => {
+                let key = matched.iter().find_map(|index| {
+                    let captures = self.patterns[index].captures(line)?;
+                    Some(captures.name(group)?.as_str().to_string())
+                });
+                LineSeries::Key(key.unwrap_or_else(|| NO_GROUP_KEY.to_string()))
             }
+            None if self.args.series => LineSeries::Fixed(matched.iter().collect()),
+            None => LineSeries::Fixed(vec![0]),
+        };
+        Some((series, Some(matched)))
+    }
+
+    /// The `--value` number in a line: the `v` capture group, else the first
+    /// group, else the whole match. None if it is missing or not a number.
+    fn extract_value(&self, line: &str) -> Option<f64> {
+        let captures = self.value_pattern.as_ref()?.captures(line)?;
+        let text = captures
+            .name("v")
+            .or_else(|| captures.iter().skip(1).flatten().next())
+            .or_else(|| captures.get(0))?;
+        text.as_str().trim().parse().ok()
+    }
+
+    /// Whether an event is recent enough for the `--window`, if there is one
+    fn in_window(&self, timestamp: DateTime<Utc>) -> bool {
+        self.window
+            .is_none_or(|window| timestamp >= Utc::now() - window)
+    }
+
+    /// Range to display: `--since`/`--until`, narrowed to the sliding window
+    /// in follow mode so the time axis ends at the latest refresh
+    fn display_range(&self) -> TimeRange {
+        match (self.window, self.window_end) {
+            (Some(window), Some(end)) => self.range.within_window(window, end),
+            _ => self.range,
+        }
+    }
+
+    /// Time range shown in summaries and charts: the sliding window if there
+    /// is one, otherwise the first and last counted event
+    fn time_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        match self.display_range() {
+            TimeRange {
+                since: Some(since),
+                until: Some(until),
+            } if self.window.is_some() => Some((since, until)),
+            _ => self.bucket.time_range(),
+        }
+    }
+
+    /// Buckets to display, zero-filled when `--fill` is in effect
+    fn buckets(&self) -> Result<Vec<(DateTime<Utc>, usize)>> {
+        let range = self.display_range();
+        if self.args.fill() {
+            self.bucket.get_filled_buckets(range.since, range.until)
+        } else {
+            Ok(self.bucket.get_buckets())
+        }
+    }
+
+    /// Per-series buckets to display, zero-filled when `--fill` is in effect
+    fn series_buckets(&self) -> Result<Vec<SeriesRow>> {
+        let range = self.display_range();
+        if self.args.fill() {
+            self.bucket
+                .get_filled_series_buckets(range.since, range.until)
+        } else {
+            Ok(self.bucket.get_series_buckets())
+        }
+    }
+
+    /// Per-series `--value` aggregates, with the same rows as `series_buckets`
+    fn series_aggregates(&self) -> Result<Vec<AggregateRow>> {
+        let range = self.display_range();
+        if self.args.fill() {
+            self.bucket
+                .get_filled_series_aggregates(range.since, range.until)
+        } else {
+            Ok(self.bucket.get_series_aggregates())
+        }
+    }
+
+    fn output_results(&self) -> Result<()> {
+        if let Some(agg) = self.bucket.aggregation() {
+            return self.output_value_results(agg);
+        }
+        if self.args.series || self.args.group_by.is_some() || self.args.per_file {
+            return self.output_series_results();
+        }
+
+        let buckets = self.buckets()?;
+        let bucket_size = self.bucket.bucket_size();
+        let time_range = self.time_range();
+        let tz = self.bucket.timezone();
+
+        match self.args.output_format() {
+            OutputFormat::Table => output_table(&buckets, &bucket_size, &tz),
+            OutputFormat::Csv => output_csv(&buckets, &tz, self.args.no_headers),
+            OutputFormat::Json => output_json(
+                &buckets,
+                &bucket_size,
+                &tz,
+                time_range,
+                &self.timestamp_formats,
+            ),
             OutputFormat::AsciiPlot => {
-                print!("\x1B[2J\x1B[1;1H"); // Clear screen
-                let time_range = self.bucket.time_range();
-                let bucket_size = self.bucket.bucket_size_seconds();
+                let time_range = self.time_range();
+                let bucket_size = self.bucket.bucket_size();
                 let pattern = self.args.get_pattern().unwrap_or("(no pattern)");
                 let files = &self.args.files;
-                let _ = plot_ascii(
+                plot_ascii(
                     &buckets,
                     time_range,
-                    bucket_size,
+                    &bucket_size,
+                    &tz,
                     pattern,
                     files,
                     self.args.y_zero,
-                );
+                )
             }
             OutputFormat::Png => {
-                // PNG in follow mode doesn't make much sense, but handle it
                 if let Some(ref png_file) = self.args.png {
-                    let _ = plot_png(&buckets, png_file);
+                    plot_png(&buckets, &bucket_size, &tz, png_file)
+                } else {
+                    anyhow::bail!("PNG output requires --png <file> argument")
                 }
             }
         }
-
-        Ok(())
     }
 
-    fn matches_patterns(&self, line: &str) -> bool {
-        if self.patterns.is_empty() {
-            // No patterns means match everything (when --no-default-pattern is used)
-            true
+    fn output_series_results(&self) -> Result<()> {
+        let mut rows = self.series_buckets()?;
+        let mut series = self.bucket.series_names().to_vec();
+        let bucket_size = self.bucket.bucket_size();
+        let time_range = self.time_range();
+        let tz = self.bucket.timezone();
+
+        // Groups are ordered by frequency, and plotted stacked since they
+        // partition the matches
+        let grouped = self.args.group_by.is_some();
+        if grouped {
+            let (names, columns) = rank_series(&series, &rows, self.args.top.map(|n| n as usize));
+            rows = regroup_series(&rows, &columns);
+            series = names;
+        }
+        let plotted_rows = if grouped {
+            stack_series(&rows)
         } else {
-            // Line must match at least one pattern
-            self.patterns.iter().any(|p| p.is_match(line))
+            rows.clone()
+        };
+
+        match self.args.output_format() {
+            OutputFormat::Table => output_series_table(&series, &rows, &bucket_size, &tz),
+            OutputFormat::Csv => output_series_csv(&series, &rows, &tz, self.args.no_headers),
+            OutputFormat::Json => match self.args.group_by {
+                Some(ref group) => output_grouped_json(
+                    group,
+                    &series,
+                    &rows,
+                    self.bucket.total_lines(),
+                    &bucket_size,
+                    &tz,
+                    time_range,
+                    &self.timestamp_formats,
+                ),
+                None => output_series_json(
+                    &series,
+                    &rows,
+                    self.bucket.total_lines(),
+                    &bucket_size,
+                    &tz,
+                    time_range,
+                    &self.timestamp_formats,
+                ),
+            },
+            OutputFormat::AsciiPlot => plot_ascii_series(
+                &series,
+                &plotted_rows,
+                time_range,
+                &bucket_size,
+                &tz,
+                &self.args.files,
+                self.args.y_zero,
+            ),
+            OutputFormat::Png => {
+                if let Some(ref png_file) = self.args.png {
+                    plot_png_series(&series, &plotted_rows, &bucket_size, &tz, png_file)
+                } else {
+                    anyhow::bail!("PNG output requires --png <file> argument")
+                }
+            }
         }
     }
 
-    fn output_results(&self) -> Result<()> {
-        let buckets = self.bucket.get_buckets();
-        let bucket_size = self.bucket.bucket_size_seconds();
-        let time_range = self.bucket.time_range();
+    fn output_value_results(&self, agg: AggFn) -> Result<()> {
+        let mut counts = self.series_buckets()?;
+        let mut aggregates = self.series_aggregates()?;
+        let mut series = self.bucket.series_names().to_vec();
+        let bucket_size = self.bucket.bucket_size();
+        let time_range = self.time_range();
+        let tz = self.bucket.timezone();
+
+        let grouped = self.args.group_by.is_some();
+        if grouped {
+            let (names, columns) = rank_series(&series, &counts, self.args.top.map(|n| n as usize));
+            counts = regroup_series(&counts, &columns);
+            aggregates = regroup_series(&aggregates, &columns);
+            series = names;
+        }
+        let totals = aggregate_totals(&aggregates, agg);
+        let values = aggregate_values(&aggregates, agg);
+
+        // Charts need a label for the single series too. Only sums of
+        // disjoint groups can be stacked.
+        let value_regex = self.args.value.as_deref().unwrap_or_default();
+        let y_label = format!("{} of {}", agg, value_regex);
+        let labels = if series.is_empty() {
+            vec![agg.to_string()]
+        } else {
+            series.clone()
+        };
+        let plotted_values = if grouped && agg.is_additive() {
+            stack_values(&values)
+        } else {
+            values.clone()
+        };
 
         match self.args.output_format() {
-            OutputFormat::Table => output_table(&buckets, bucket_size),
-            OutputFormat::Csv => output_csv(&buckets, self.args.no_headers),
-            OutputFormat::Json => output_json(&buckets, bucket_size, time_range),
+            OutputFormat::Table => {
+                output_value_table(&series, &counts, &values, &totals, agg, &bucket_size, &tz)
+            }
+            OutputFormat::Csv => {
+                output_value_csv(&series, &counts, &values, agg, &tz, self.args.no_headers)
+            }
+            OutputFormat::Json => output_value_json(
+                &series,
+                &counts,
+                self.bucket.total_lines(),
+                &values,
+                &totals,
+                agg,
+                self.args.group_by.as_deref(),
+                &bucket_size,
+                &tz,
+                time_range,
+                &self.timestamp_formats,
+            ),
             OutputFormat::AsciiPlot => {
-                let time_range = self.bucket.time_range();
-                let bucket_size = self.bucket.bucket_size_seconds();
-                let pattern = self.args.get_pattern().unwrap_or("(no pattern)");
-                let files = &self.args.files;
-                plot_ascii(
-                    &buckets,
+                let title = match self.args.get_pattern() {
+                    Some(pattern) if series.is_empty() => {
+                        format!("Pattern: \"{}\" | {}", pattern, y_label)
+                    }
+                    _ => format!("Series: {} | {}", labels.join(", "), y_label),
+                };
+                plot_ascii_values(
+                    &labels,
+                    &plotted_values,
                     time_range,
-                    bucket_size,
-                    pattern,
-                    files,
+                    &bucket_size,
+                    &tz,
+                    &title,
+                    &y_label,
+                    &self.args.files,
                     self.args.y_zero,
                 )
             }
             OutputFormat::Png => {
                 if let Some(ref png_file) = self.args.png {
-                    plot_png(&buckets, png_file)
+                    plot_png_values(
+                        &labels,
+                        &plotted_values,
+                        &bucket_size,
+                        &tz,
+                        &y_label,
+                        png_file,
+                    )
                 } else {
                     anyhow::bail!("PNG output requires --png <file> argument")
                 }
@@ -399,3 +1361,18 @@ impl LogProcessor {
         }
     }
 }
+
+/// When a file was last modified; None for stdin or if unknown
+fn modified_time(path: &str) -> Option<DateTime<Utc>> {
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()?;
+    Some(modified.into())
+}
+
+/// Add per-pattern counts element by element
+fn add_counts(total: &mut [usize], counts: &[usize]) {
+    for (total, count) in total.iter_mut().zip(counts) {
+        *total += count;
+    }
+}
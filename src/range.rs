@@ -0,0 +1,303 @@
+use crate::bucket::parse_duration;
+use crate::timezone::{local_to_utc, DstPolicy};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+/// Naive date-time layouts accepted by `--since`/`--until`
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Time-of-day layouts accepted after `today`/`yesterday`/`tomorrow`
+const TIME_FORMATS: &[&str] = &["%H:%M:%S", "%H:%M"];
+
+/// Event-time window from `--since` (inclusive) and `--until` (exclusive)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl TimeRange {
+    /// Parse both bounds relative to `now`; dates and times without an
+    /// offset are wall-clock time in `tz`
+    pub fn parse(
+        since: Option<&str>,
+        until: Option<&str>,
+        now: DateTime<Utc>,
+        tz: &Tz,
+    ) -> Result<Self> {
+        let range = Self {
+            since: since.map(|s| parse_time_expr(s, now, tz)).transpose()?,
+            until: until.map(|s| parse_time_expr(s, now, tz)).transpose()?,
+        };
+
+        if let (Some(since), Some(until)) = (range.since, range.until) {
+            if since >= until {
+                anyhow::bail!(
+                    "--since ({}) must be earlier than --until ({})",
+                    since.to_rfc3339(),
+                    until.to_rfc3339()
+                );
+            }
+        }
+
+        Ok(range)
+    }
+
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| timestamp >= since)
+            && self.until.is_none_or(|until| timestamp < until)
+    }
+
+    /// Whether `timestamp` is at or after `--until`
+    pub fn is_past(&self, timestamp: DateTime<Utc>) -> bool {
+        self.until.is_some_and(|until| timestamp >= until)
+    }
+
+    /// The part of this range inside the sliding `window` that ends at `now`
+    pub fn within_window(&self, window: Duration, now: DateTime<Utc>) -> Self {
+        let start = now - window;
+        Self {
+            since: Some(self.since.map_or(start, |since| since.max(start))),
+            until: Some(self.until.map_or(now, |until| until.min(now))),
+        }
+    }
+}
+
+/// Parse a `--window` length such as `15m` or `1h30m`. It must be short
+/// enough that the window's start, `now - window`, is a representable time.
+pub fn parse_window(expr: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid --window '{}': expected a positive duration like 15m or 1h30m",
+            expr
+        )
+    };
+    let micros = parse_duration(expr.trim())
+        .filter(|s| *s > 0.0)
+        .map(|seconds| seconds * 1_000_000.0)
+        .filter(|micros| *micros < i64::MAX as f64)
+        .ok_or_else(invalid)?;
+    let window = Duration::microseconds(micros as i64);
+    Utc::now().checked_sub_signed(window).ok_or_else(invalid)?;
+    Ok(window)
+}
+
+/// Parse a `--since`/`--until` expression: an RFC 3339 instant, a date or
+/// date-time, a relative offset from now (`-2h`, `+30m`, `90m ago`), `now`,
+/// or `today`/`yesterday`/`tomorrow` with an optional time of day.
+pub fn parse_time_expr(expr: &str, now: DateTime<Utc>, tz: &Tz) -> Result<DateTime<Utc>> {
+    let trimmed = expr.trim();
+    let lower = trimmed.to_lowercase();
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid time '{}': expected an RFC 3339 time, a date, a relative time like -2h, or today/yesterday [HH:MM]",
+            expr
+        )
+    };
+
+    if lower == "now" {
+        return Ok(now);
+    }
+
+    // Relative to now
+    let relative = match (lower.strip_prefix('-'), lower.strip_prefix('+')) {
+        (Some(rest), _) => Some((-1.0, rest)),
+        (_, Some(rest)) => Some((1.0, rest)),
+        _ => lower.strip_suffix(" ago").map(|rest| (-1.0, rest.trim())),
+    };
+    if let Some((sign, duration)) = relative {
+        let seconds = parse_duration(duration)
+            .filter(|s| *s >= 0.0)
+            .ok_or_else(invalid)?;
+        // Offsets beyond the representable range are rejected, not wrapped
+        let micros = sign * seconds * 1_000_000.0;
+        if micros.abs() >= i64::MAX as f64 {
+            return Err(invalid());
+        }
+        return now
+            .checked_add_signed(Duration::microseconds(micros as i64))
+            .ok_or_else(invalid);
+    }
+
+    // Named days, optionally followed by a time of day
+    let mut words = lower.splitn(2, char::is_whitespace);
+    let day_offset = match words.next() {
+        Some("today") => Some(0),
+        Some("yesterday") => Some(-1),
+        Some("tomorrow") => Some(1),
+        _ => None,
+    };
+    if let Some(offset) = day_offset {
+        let date = now.with_timezone(tz).date_naive() + Duration::days(offset);
+        let time = match words.next().map(str::trim) {
+            Some(time) => TIME_FORMATS
+                .iter()
+                .find_map(|fmt| NaiveTime::parse_from_str(time, fmt).ok())
+                .ok_or_else(invalid)?,
+            None => NaiveTime::MIN,
+        };
+        return local_time(tz, date.and_time(time)).ok_or_else(invalid);
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(trimmed) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    if let Some(ndt) = DATETIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(trimmed, fmt).ok())
+    {
+        return local_time(tz, ndt).ok_or_else(invalid);
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+        return local_time(tz, date.and_time(NaiveTime::MIN)).ok_or_else(invalid);
+    }
+
+    Err(invalid())
+}
+
+/// Wall-clock bound in `tz`; a bound inside a DST gap moves to the jump
+fn local_time(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    local_to_utc(tz, local, DstPolicy::Latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 10, 3, 14, 30, 0).unwrap()
+    }
+
+    fn parse(expr: &str) -> DateTime<Utc> {
+        parse_time_expr(expr, now(), &Tz::UTC).unwrap()
+    }
+
+    #[test]
+    fn test_parse_absolute_times() {
+        assert_eq!(
+            parse("2025-10-01T12:00:00+02:00"),
+            Utc.with_ymd_and_hms(2025, 10, 1, 10, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse("2025-10-01 12:15"),
+            Utc.with_ymd_and_hms(2025, 10, 1, 12, 15, 0).unwrap()
+        );
+        assert_eq!(
+            parse("2025-10-01"),
+            Utc.with_ymd_and_hms(2025, 10, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_relative_times() {
+        assert_eq!(parse("now"), now());
+        assert_eq!(
+            parse("-2h"),
+            Utc.with_ymd_and_hms(2025, 10, 3, 12, 30, 0).unwrap()
+        );
+        assert_eq!(
+            parse("+1h30m"),
+            Utc.with_ymd_and_hms(2025, 10, 3, 16, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse("90m ago"),
+            Utc.with_ymd_and_hms(2025, 10, 3, 13, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_named_days() {
+        assert_eq!(
+            parse("today"),
+            Utc.with_ymd_and_hms(2025, 10, 3, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse("yesterday"),
+            Utc.with_ymd_and_hms(2025, 10, 2, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse("Today 09:00"),
+            Utc.with_ymd_and_hms(2025, 10, 3, 9, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse("tomorrow 06:30:15"),
+            Utc.with_ymd_and_hms(2025, 10, 4, 6, 30, 15).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_named_days_use_timezone() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        // Local midnight in BST is 23:00 UTC the day before
+        assert_eq!(
+            parse_time_expr("today", now(), &tz).unwrap(),
+            Utc.with_ymd_and_hms(2025, 10, 2, 23, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_time_expr("2025-10-01 12:00", now(), &tz).unwrap(),
+            Utc.with_ymd_and_hms(2025, 10, 1, 11, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_times() {
+        for expr in [
+            "",
+            "soon",
+            "-2parsecs",
+            "today 25:00",
+            "2025-13-01",
+            "--1h",
+            "-100000000w",
+            "+14000000w",
+            "+1e300",
+            "1e300s ago",
+        ] {
+            assert!(parse_time_expr(expr, now(), &Tz::UTC).is_err(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_time_range() {
+        let range = TimeRange::parse(Some("-1h"), Some("now"), now(), &Tz::UTC).unwrap();
+        assert!(range.contains(Utc.with_ymd_and_hms(2025, 10, 3, 13, 30, 0).unwrap()));
+        assert!(range.contains(Utc.with_ymd_and_hms(2025, 10, 3, 14, 0, 0).unwrap()));
+        assert!(!range.contains(now()));
+        assert!(range.is_past(now()));
+        assert!(!range.contains(Utc.with_ymd_and_hms(2025, 10, 3, 13, 29, 59).unwrap()));
+
+        assert!(TimeRange::default().contains(now()));
+        assert!(!TimeRange::default().is_past(now()));
+        assert!(TimeRange::parse(Some("now"), Some("-1h"), now(), &Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_within_window() {
+        let window = parse_window("15m").unwrap();
+        assert_eq!(window, Duration::minutes(15));
+        assert_eq!(
+            TimeRange::default().within_window(window, now()),
+            TimeRange {
+                since: Some(Utc.with_ymd_and_hms(2025, 10, 3, 14, 15, 0).unwrap()),
+                until: Some(now()),
+            }
+        );
+
+        // --since inside the window still applies
+        let range = TimeRange::parse(Some("-5m"), None, now(), &Tz::UTC).unwrap();
+        assert_eq!(range.within_window(window, now()).since, range.since);
+
+        for expr in ["0", "-5m", "soon", "100000000w", "14000000w", "1e300"] {
+            assert!(parse_window(expr).is_err(), "{expr}");
+        }
+    }
+}
@@ -1,51 +1,448 @@
 use anyhow::Result;
-use flate2::read::GzDecoder;
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use glob::MatchOptions;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{stdin, BufRead, BufReader};
+use std::io::{stdin, BufRead, BufReader, Chain, Cursor, Read, Seek, SeekFrom, Stdin, Take};
+use std::path::{Path, PathBuf};
+use xz2::read::XzDecoder;
+
+/// Number of leading bytes inspected to detect the compression format
+const MAGIC_LEN: usize = 10;
+
+/// Compression format detected from a stream's magic bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect the compression format from the first bytes of a stream
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if is_bzip2_header(magic) {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// "BZh" followed by a block size digit and either a block or end-of-stream
+/// magic, so plain text that happens to start with "BZh" is not misdetected
+fn is_bzip2_header(magic: &[u8]) -> bool {
+    const BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+    const EOS_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+    magic.len() >= MAGIC_LEN
+        && magic.starts_with(b"BZh")
+        && (b'1'..=b'9').contains(&magic[3])
+        && (magic[4..10] == BLOCK_MAGIC || magic[4..10] == EOS_MAGIC)
+}
+
+/// Stdin with the bytes already consumed for format detection put back in front
+pub type StdinSource = Chain<Cursor<Vec<u8>>, Stdin>;
+
+/// Raw byte source underneath a decompressor
+pub enum Input {
+    File(File),
+    Stdin(StdinSource),
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Input::File(file) => file.read(buf),
+            Input::Stdin(stdin) => stdin.read(buf),
+        }
+    }
+}
 
 pub enum LogReader {
     PlainFile(BufReader<File>),
-    GzipFile(BufReader<GzDecoder<File>>),
-    Stdin(BufReader<std::io::Stdin>),
+    /// A byte range of an uncompressed file, aligned to line boundaries
+    FileChunk(BufReader<Take<File>>),
+    Gzip(BufReader<MultiGzDecoder<Input>>),
+    Zstd(BufReader<zstd::Decoder<'static, BufReader<Input>>>),
+    Xz(BufReader<XzDecoder<Input>>),
+    Bzip2(BufReader<MultiBzDecoder<Input>>),
+    Stdin(BufReader<StdinSource>),
+    /// Stdin before its first bytes have been inspected. Detection happens on
+    /// the first call to `lines()` so creating a reader never blocks.
+    PendingStdin,
 }
 
 impl LogReader {
+    /// Open a file (or stdin when `path` is `None`), detecting gzip, zstd, xz
+    /// and bzip2 compression from the content rather than the file name
     pub fn new(path: Option<&str>) -> Result<Self> {
         match path {
-            None => Ok(LogReader::Stdin(BufReader::new(stdin()))),
+            None => Ok(LogReader::PendingStdin),
             Some(p) => {
-                let file = File::open(p)?;
-                if p.ends_with(".gz") {
-                    let decoder = GzDecoder::new(file);
-                    Ok(LogReader::GzipFile(BufReader::new(decoder)))
-                } else {
-                    Ok(LogReader::PlainFile(BufReader::new(file)))
+                let mut file = File::open(p)?;
+                let magic = read_magic(&mut file)?;
+                file.seek(SeekFrom::Start(0))?;
+                match Compression::detect(&magic) {
+                    Compression::None => Ok(LogReader::PlainFile(BufReader::new(file))),
+                    compression => Self::decompress(Input::File(file), compression),
                 }
             }
         }
     }
 
+    /// Open the `start..end` byte range of an uncompressed file
+    pub fn open_range(path: &str, start: u64, end: u64) -> Result<Self> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(start))?;
+        Ok(LogReader::FileChunk(BufReader::new(file.take(end - start))))
+    }
+
+    fn open_stdin() -> Result<Self> {
+        let mut handle = stdin();
+        let magic = read_magic(&mut handle)?;
+        let compression = Compression::detect(&magic);
+        let source = Cursor::new(magic).chain(handle);
+        if compression == Compression::None {
+            Ok(LogReader::Stdin(BufReader::new(source)))
+        } else {
+            Self::decompress(Input::Stdin(source), compression)
+        }
+    }
+
+    fn decompress(input: Input, compression: Compression) -> Result<Self> {
+        Ok(match compression {
+            Compression::Gzip => LogReader::Gzip(BufReader::new(MultiGzDecoder::new(input))),
+            Compression::Zstd => LogReader::Zstd(BufReader::new(zstd::Decoder::new(input)?)),
+            Compression::Xz => LogReader::Xz(BufReader::new(XzDecoder::new_multi_decoder(input))),
+            Compression::Bzip2 => LogReader::Bzip2(BufReader::new(MultiBzDecoder::new(input))),
+            Compression::None => unreachable!("plain input does not need a decoder"),
+        })
+    }
+
+    pub fn compression(&self) -> Compression {
+        match self {
+            LogReader::PlainFile(_)
+            | LogReader::FileChunk(_)
+            | LogReader::Stdin(_)
+            | LogReader::PendingStdin => Compression::None,
+            LogReader::Gzip(_) => Compression::Gzip,
+            LogReader::Zstd(_) => Compression::Zstd,
+            LogReader::Xz(_) => Compression::Xz,
+            LogReader::Bzip2(_) => Compression::Bzip2,
+        }
+    }
+
     pub fn lines(&mut self) -> Box<dyn Iterator<Item = Result<String>> + '_> {
+        if let LogReader::PendingStdin = self {
+            match Self::open_stdin() {
+                Ok(reader) => *self = reader,
+                Err(e) => return Box::new(std::iter::once(Err(e))),
+            }
+        }
+
         match self {
             LogReader::PlainFile(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
-            LogReader::GzipFile(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
+            LogReader::FileChunk(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
+            LogReader::Gzip(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
+            LogReader::Zstd(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
+            LogReader::Xz(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
+            LogReader::Bzip2(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
             LogReader::Stdin(reader) => Box::new(reader.lines().map(|r| r.map_err(Into::into))),
+            LogReader::PendingStdin => unreachable!("stdin is opened above"),
+        }
+    }
+}
+
+/// Join continuation lines onto the record they follow, so a multiline event
+/// such as a stack trace is yielded as one newline-separated string. A line
+/// for which `starts_record` is false is a continuation, unless nothing
+/// precedes it.
+pub fn join_records<I, F>(lines: I, starts_record: F) -> RecordJoiner<I, F>
+where
+    I: Iterator<Item = Result<String>>,
+    F: FnMut(&str) -> bool,
+{
+    RecordJoiner {
+        lines: lines.peekable(),
+        starts_record,
+    }
+}
+
+/// Iterator returned by `join_records`
+pub struct RecordJoiner<I: Iterator, F> {
+    lines: std::iter::Peekable<I>,
+    starts_record: F,
+}
+
+impl<I, F> Iterator for RecordJoiner<I, F>
+where
+    I: Iterator<Item = Result<String>>,
+    F: FnMut(&str) -> bool,
+{
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = match self.lines.next()? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+
+        while let Some(Ok(line)) = self.lines.peek() {
+            if (self.starts_record)(line) {
+                break;
+            }
+            record.push('\n');
+            record.push_str(line);
+            self.lines.next();
         }
+
+        Some(Ok(record))
     }
 }
 
+/// Split an uncompressed file into at most `max_chunks` byte ranges of at
+/// least `min_chunk_size` bytes, each ending just after a newline so no line
+/// is split between chunks. Small files yield a single range.
+pub fn split_into_chunks(
+    path: &str,
+    max_chunks: usize,
+    min_chunk_size: u64,
+) -> Result<Vec<(u64, u64)>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let chunks = (size / min_chunk_size.max(1)).clamp(1, max_chunks.max(1) as u64);
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for i in 1..chunks {
+        let target = size * i / chunks;
+        if target <= start {
+            continue;
+        }
+
+        // Advance to the byte after the next newline
+        file.seek(SeekFrom::Start(target))?;
+        let mut reader = BufReader::new(&mut file);
+        let mut skipped = Vec::new();
+        let read = reader.read_until(b'\n', &mut skipped)? as u64;
+        let boundary = target + read;
+        if boundary >= size {
+            break;
+        }
+        ranges.push((start, boundary));
+        start = boundary;
+    }
+    ranges.push((start, size));
+
+    Ok(ranges)
+}
+
+/// Read up to `MAGIC_LEN` bytes, retrying short reads so pipes delivering a
+/// few bytes at a time are still sniffed correctly
+fn read_magic(reader: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    reader.take(MAGIC_LEN as u64).read_to_end(&mut magic)?;
+    Ok(magic)
+}
+
 /// Create readers for multiple files or stdin
 pub fn create_readers(files: &[String]) -> Result<Vec<(Option<String>, LogReader)>> {
     if files.is_empty() {
         Ok(vec![(None, LogReader::new(None)?)])
     } else {
-        files
-            .iter()
-            .map(|f| Ok((Some(f.clone()), LogReader::new(Some(f))?)))
+        expand_files(files)?
+            .into_iter()
+            .map(|f| {
+                let reader = LogReader::new(Some(&f))?;
+                Ok((Some(f), reader))
+            })
             .collect()
     }
 }
 
+/// Expand FILES arguments into a list of concrete file paths.
+///
+/// Each argument may be a plain path, a directory (walked recursively), or a
+/// glob pattern with `*`, `?`, `[...]`, `**` and `{a,b}` brace sets. Matches
+/// for each pattern are sorted, arguments keep their command-line order, and
+/// duplicates are dropped. A glob that matches nothing is an error; a plain
+/// path is passed through unchanged so opening it reports the usual error.
+pub fn expand_files(args: &[String]) -> Result<Vec<String>> {
+    expand_paths(args, false)
+}
+
+/// Expand FILES like `expand_files`, except that a glob matching nothing is
+/// not an error: in follow mode matching files may still be created
+pub fn expand_follow_files(args: &[String]) -> Result<Vec<String>> {
+    expand_paths(args, true)
+}
+
+/// Whether any FILES argument is a glob or directory, whose files can change
+/// while they are followed
+pub fn has_dynamic_files(args: &[String]) -> bool {
+    args.iter()
+        .flat_map(|arg| expand_braces(arg))
+        .any(|pattern| is_glob_pattern(&pattern) || Path::new(&pattern).is_dir())
+}
+
+fn expand_paths(args: &[String], allow_empty: bool) -> Result<Vec<String>> {
+    let mut files: Vec<String> = Vec::new();
+    // Canonical paths already listed, so one file reached by two paths is
+    // read once
+    let mut seen = HashSet::new();
+
+    for arg in args {
+        let mut matched = Vec::new();
+
+        for pattern in expand_braces(arg) {
+            if is_glob_pattern(&pattern) {
+                let options = MatchOptions {
+                    case_sensitive: true,
+                    require_literal_separator: true,
+                    require_literal_leading_dot: true,
+                };
+                let mut paths = Vec::new();
+                for entry in glob::glob_with(&pattern, options)
+                    .map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", pattern, e))?
+                {
+                    let path = entry?;
+                    // Directories matched by a glob are skipped; only explicit
+                    // directory arguments are walked
+                    if path.is_file() {
+                        paths.push(path);
+                    }
+                }
+                paths.sort();
+                matched.extend(paths);
+            } else {
+                let path = Path::new(&pattern);
+                if path.is_dir() {
+                    let mut paths = Vec::new();
+                    collect_dir_files(path, &mut paths)?;
+                    paths.sort();
+                    matched.extend(paths);
+                } else {
+                    matched.push(path.to_path_buf());
+                }
+            }
+        }
+
+        if matched.is_empty() && !allow_empty {
+            anyhow::bail!("No files matched: {}", arg);
+        }
+
+        for path in matched {
+            // Paths that do not exist yet are compared as written
+            let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if seen.insert(canonical) {
+                files.push(path.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Recursively collect regular files below a directory. Symlinks to files
+/// are collected, but symlinked directories are not walked, so a link back
+/// up the tree cannot make the walk loop.
+fn collect_dir_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_dir_files(&path, out)?;
+        } else if file_type.is_file() || (file_type.is_symlink() && path.is_file()) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expand shell-style brace sets, e.g. `app.{log,log.1}` -> `app.log`, `app.log.1`.
+/// Braces without a top-level comma are kept literally.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let bytes = pattern.as_bytes();
+    let mut depth = 0;
+    let mut open = None;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'{' => {
+                if depth == 0 {
+                    open = Some(i);
+                }
+                depth += 1;
+            }
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let start = open.unwrap();
+                    let inner = &pattern[start + 1..i];
+                    let alternatives = split_top_level_commas(inner);
+                    if alternatives.len() < 2 {
+                        // Not a brace set; keep it literally and expand the rest
+                        let prefix = &pattern[..=i];
+                        return expand_braces(&pattern[i + 1..])
+                            .into_iter()
+                            .map(|rest| format!("{}{}", prefix, rest))
+                            .collect();
+                    }
+
+                    let prefix = &pattern[..start];
+                    let suffixes = expand_braces(&pattern[i + 1..]);
+                    let mut result = Vec::new();
+                    for alt in alternatives {
+                        for expanded in expand_braces(alt) {
+                            for suffix in &suffixes {
+                                result.push(format!("{}{}{}", prefix, expanded, suffix));
+                            }
+                        }
+                    }
+                    return result;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    vec![pattern.to_string()]
+}
+
+fn split_top_level_commas(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut last = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[last..]);
+    parts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,6 +490,126 @@ mod tests {
         std::fs::remove_file(temp_path).unwrap();
     }
 
+    #[test]
+    fn test_reader_multi_member_gzip() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression as GzLevel;
+
+        // Two gzip members concatenated, as produced by `cat a.gz b.gz`
+        let mut data = Vec::new();
+        for line in ["Member 1", "Member 2"] {
+            let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+            writeln!(encoder, "{}", line).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&data).unwrap();
+
+        let mut reader = LogReader::new(Some(temp_file.path().to_str().unwrap())).unwrap();
+        let lines: Vec<_> = reader.lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines, vec!["Member 1", "Member 2"]);
+    }
+
+    #[test]
+    fn test_reader_detects_compression_without_extension() {
+        let text = b"2025-10-03T12:00:00Z ERROR one\n2025-10-03T12:01:00Z ERROR two\n";
+
+        let gzip = {
+            let mut e = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            e.write_all(text).unwrap();
+            e.finish().unwrap()
+        };
+        let zstd = zstd::encode_all(&text[..], 0).unwrap();
+        let xz = {
+            let mut e = xz2::write::XzEncoder::new(Vec::new(), 6);
+            e.write_all(text).unwrap();
+            e.finish().unwrap()
+        };
+        let bzip2 = {
+            let mut e = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            e.write_all(text).unwrap();
+            e.finish().unwrap()
+        };
+
+        for (data, expected) in [
+            (gzip, Compression::Gzip),
+            (zstd, Compression::Zstd),
+            (xz, Compression::Xz),
+            (bzip2, Compression::Bzip2),
+            (text.to_vec(), Compression::None),
+        ] {
+            // logrotate dateext style name with no compression extension
+            let mut temp_file = NamedTempFile::new().unwrap();
+            temp_file.write_all(&data).unwrap();
+
+            let mut reader = LogReader::new(Some(temp_file.path().to_str().unwrap())).unwrap();
+            assert_eq!(reader.compression(), expected);
+            let lines: Vec<_> = reader.lines().map(|l| l.unwrap()).collect();
+            assert_eq!(lines.len(), 2, "{:?}", expected);
+            assert!(lines[1].ends_with("ERROR two"));
+        }
+    }
+
+    #[test]
+    fn test_compression_detect() {
+        assert_eq!(Compression::detect(b""), Compression::None);
+        assert_eq!(Compression::detect(&[0x1f]), Compression::None);
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        // Plain text starting with "BZh" is not bzip2
+        assert_eq!(Compression::detect(b"BZh9 hello world"), Compression::None);
+    }
+
+    #[test]
+    fn test_split_into_chunks_on_line_boundaries() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        for i in 0..1000 {
+            writeln!(temp_file, "2025-10-03T12:00:00Z line {}", i).unwrap();
+        }
+        temp_file.flush().unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let ranges = split_into_chunks(path, 4, 1024).unwrap();
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[0].0, 0);
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0);
+        }
+
+        let mut lines = Vec::new();
+        for (start, end) in ranges {
+            let mut reader = LogReader::open_range(path, start, end).unwrap();
+            lines.extend(reader.lines().map(|l| l.unwrap()));
+        }
+        assert_eq!(lines.len(), 1000);
+        assert_eq!(lines[999], "2025-10-03T12:00:00Z line 999");
+
+        // Files smaller than the minimum chunk size are not split
+        assert_eq!(split_into_chunks(path, 4, 1 << 30).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_join_records() {
+        let lines = [
+            "  orphan continuation",
+            "2025-10-03 ERROR boom",
+            "java.lang.IllegalStateException: boom",
+            "\tat Foo.bar(Foo.java:1)",
+            "2025-10-03 INFO ok",
+        ]
+        .map(|line| Ok(line.to_string()));
+        let records: Vec<String> = join_records(lines.into_iter(), |line| line.starts_with("2025"))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                "  orphan continuation",
+                "2025-10-03 ERROR boom\njava.lang.IllegalStateException: boom\n\tat Foo.bar(Foo.java:1)",
+                "2025-10-03 INFO ok",
+            ]
+        );
+    }
+
     #[test]
     fn test_reader_nonexistent_file() {
         let result = LogReader::new(Some("/nonexistent/file.log"));
@@ -134,4 +651,110 @@ mod tests {
         let result = create_readers(&files);
         assert!(result.is_err());
     }
+
+    fn touch(dir: &Path, name: &str) -> String {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        File::create(&path).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("app.log"), vec!["app.log"]);
+        assert_eq!(expand_braces("app.{log,txt}"), vec!["app.log", "app.txt"]);
+        assert_eq!(
+            expand_braces("{a,b}/{1,2}"),
+            vec!["a/1", "a/2", "b/1", "b/2"]
+        );
+        assert_eq!(expand_braces("x{a,b{c,d}}"), vec!["xa", "xbc", "xbd"]);
+        assert_eq!(expand_braces("lit{eral}"), vec!["lit{eral}"]);
+    }
+
+    #[test]
+    fn test_expand_files_glob_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        let b = touch(dir.path(), "b.log");
+        let a = touch(dir.path(), "a.log");
+        touch(dir.path(), "c.txt");
+
+        let pattern = format!("{}/*.log", dir.path().display());
+        let files = expand_files(&[pattern]).unwrap();
+        assert_eq!(files, vec![a, b]);
+    }
+
+    #[test]
+    fn test_expand_files_recursive_and_braces() {
+        let dir = tempfile::tempdir().unwrap();
+        let top = touch(dir.path(), "app.log");
+        let nested = touch(dir.path(), "svc/deep/app.log");
+        let rotated = touch(dir.path(), "svc/app.log.1");
+        touch(dir.path(), "svc/app.txt");
+
+        let pattern = format!("{}/**/app.{{log,log.1}}", dir.path().display());
+        let files = expand_files(&[pattern]).unwrap();
+        assert_eq!(files, vec![top, nested, rotated]);
+    }
+
+    #[test]
+    fn test_expand_files_directory_argument() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = touch(dir.path(), "a.log");
+        let b = touch(dir.path(), "sub/b.log");
+
+        let files = expand_files(&[dir.path().to_str().unwrap().to_string()]).unwrap();
+        assert_eq!(files, vec![a.clone(), b]);
+
+        // Duplicates across arguments are dropped, first occurrence wins
+        let files = expand_files(&[a.clone(), dir.path().to_str().unwrap().to_string()]).unwrap();
+        assert_eq!(files[0], a);
+        assert_eq!(files.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_expand_files_directory_symlink_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = touch(dir.path(), "a.log");
+        let b = touch(dir.path(), "sub/b.log");
+        std::os::unix::fs::symlink("..", dir.path().join("sub/up")).unwrap();
+        std::os::unix::fs::symlink("b.log", dir.path().join("sub/link.log")).unwrap();
+
+        let files = expand_files(&[dir.path().to_str().unwrap().to_string()]).unwrap();
+        // The link to b.log is the same file and is dropped
+        assert_eq!(files, vec![a, b]);
+    }
+
+    #[test]
+    fn test_expand_files_no_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = format!("{}/*.log", dir.path().display());
+        let err = expand_files(&[pattern]).unwrap_err();
+        assert!(err.to_string().contains("No files matched"));
+    }
+
+    #[test]
+    fn test_expand_follow_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = format!("{}/*.log", dir.path().display());
+        assert!(expand_follow_files(std::slice::from_ref(&pattern))
+            .unwrap()
+            .is_empty());
+
+        let created = touch(dir.path(), "new.log");
+        assert_eq!(
+            expand_follow_files(std::slice::from_ref(&pattern)).unwrap(),
+            vec![created.clone()]
+        );
+
+        assert!(has_dynamic_files(&[pattern]));
+        assert!(has_dynamic_files(&[dir
+            .path()
+            .to_str()
+            .unwrap()
+            .to_string()]));
+        assert!(!has_dynamic_files(&[created]));
+    }
 }
@@ -0,0 +1,364 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// Fields tried, in order, for the timestamp of a record when `--ts-field`
+/// is not given
+pub const DEFAULT_TS_FIELDS: &[&str] = &["@timestamp", "timestamp", "time", "ts"];
+
+/// Layout of structured log lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructuredFormat {
+    /// One JSON object per line (`--json-logs`)
+    Json,
+    /// `key=value` pairs with optional quoting (`--logfmt`)
+    Logfmt,
+}
+
+/// The fields of one structured log line
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Json(serde_json::Value),
+    /// Key/value pairs in line order
+    Logfmt(Vec<(String, String)>),
+}
+
+impl Record {
+    /// Parse a line in `format`; None if it is not a valid record
+    pub fn parse(format: StructuredFormat, line: &str) -> Option<Self> {
+        match format {
+            StructuredFormat::Json => Self::parse_json(line),
+            StructuredFormat::Logfmt => Self::parse_logfmt(line),
+        }
+    }
+
+    /// Parse a JSON lines record; None unless the line is a JSON object
+    pub fn parse_json(line: &str) -> Option<Self> {
+        match serde_json::from_str(line) {
+            Ok(value @ serde_json::Value::Object(_)) => Some(Record::Json(value)),
+            _ => None,
+        }
+    }
+
+    /// Parse a logfmt line such as `ts=2025-10-03T12:00:00Z level=warn
+    /// msg="disk \"sda\" full" cached`. Values may be double-quoted with
+    /// backslash escapes; a bare key is `true`. None without any `key=value`.
+    pub fn parse_logfmt(line: &str) -> Option<Self> {
+        let mut pairs = Vec::new();
+        let mut has_value = false;
+        let mut chars = line.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let mut key = String::new();
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+                key.push(c);
+            }
+            if chars.next_if_eq(&'=').is_none() {
+                pairs.push((key, "true".to_string()));
+                continue;
+            }
+
+            let mut value = String::new();
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(escaped) => value.push(escaped),
+                            None => value.push('\\'),
+                        },
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+
+            // A line of prose with a stray " = " is not a record
+            if key.is_empty() {
+                return None;
+            }
+            has_value = true;
+            pairs.push((key, value));
+        }
+
+        has_value.then_some(Record::Logfmt(pairs))
+    }
+
+    /// Look up a field by path. In JSON, dots separate nested keys
+    /// (`request.start`), but a key that itself contains dots is matched
+    /// whole first. Logfmt keys are flat and the first occurrence wins.
+    pub fn field(&self, path: &str) -> Option<FieldValue<'_>> {
+        match self {
+            Record::Json(value) => json_lookup(value, path).and_then(FieldValue::from_json),
+            Record::Logfmt(pairs) => pairs
+                .iter()
+                .find(|(key, _)| key == path)
+                .map(|(_, value)| FieldValue::Text(Cow::Borrowed(value))),
+        }
+    }
+}
+
+fn json_lookup<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    match value {
+        serde_json::Value::Object(object) => {
+            if let Some(found) = object.get(path) {
+                return Some(found);
+            }
+            path.match_indices('.').find_map(|(dot, _)| {
+                let child = object.get(&path[..dot])?;
+                json_lookup(child, &path[dot + 1..])
+            })
+        }
+        serde_json::Value::Array(items) => {
+            let (index, rest) = match path.split_once('.') {
+                Some((index, rest)) => (index, Some(rest)),
+                None => (path, None),
+            };
+            let item = items.get(index.parse::<usize>().ok()?)?;
+            match rest {
+                Some(rest) => json_lookup(item, rest),
+                None => Some(item),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A field value as seen by `--where` and `--ts-field`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue<'a> {
+    Text(Cow<'a, str>),
+    Number(f64),
+}
+
+impl<'a> FieldValue<'a> {
+    /// Strings and numbers as themselves, booleans as text, nested objects
+    /// and arrays as their JSON text; null counts as a missing field
+    fn from_json(value: &'a serde_json::Value) -> Option<Self> {
+        match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(FieldValue::Text(Cow::Borrowed(s))),
+            serde_json::Value::Number(n) => n.as_f64().map(FieldValue::Number),
+            other => Some(FieldValue::Text(Cow::Owned(other.to_string()))),
+        }
+    }
+
+    /// The value as a number, parsing numeric text
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            FieldValue::Number(n) => Some(*n),
+            FieldValue::Text(text) => text.trim().parse().ok(),
+        }
+    }
+
+    pub fn as_text(&self) -> Cow<'_, str> {
+        match self {
+            FieldValue::Number(n) => Cow::Owned(n.to_string()),
+            FieldValue::Text(text) => Cow::Borrowed(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A `--where` condition on one field, e.g. `level=error` or `status>=500`.
+/// Values compare as numbers when both sides are numeric, otherwise as text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    path: String,
+    op: CompareOp,
+    value: String,
+}
+
+impl Predicate {
+    /// Whether `record` satisfies the condition. A missing field only
+    /// satisfies `!=`.
+    pub fn matches(&self, record: &Record) -> bool {
+        let Some(field) = record.field(&self.path) else {
+            return self.op == CompareOp::Ne;
+        };
+
+        let ordering = match (field.as_number(), self.value.trim().parse::<f64>()) {
+            (Some(left), Ok(right)) => left.partial_cmp(&right),
+            _ => Some(field.as_text().as_ref().cmp(self.value.as_str())),
+        };
+        let Some(ordering) = ordering else {
+            return self.op == CompareOp::Ne;
+        };
+
+        match self.op {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid --where '{}': expected FIELD=VALUE, FIELD!=VALUE or a comparison like FIELD>=500",
+                s
+            )
+        };
+
+        let start = s.find(['=', '!', '<', '>']).ok_or_else(invalid)?;
+        let rest = &s[start..];
+        let (op, len) = if rest.starts_with(">=") {
+            (CompareOp::Ge, 2)
+        } else if rest.starts_with("<=") {
+            (CompareOp::Le, 2)
+        } else if rest.starts_with("!=") {
+            (CompareOp::Ne, 2)
+        } else if rest.starts_with("==") {
+            (CompareOp::Eq, 2)
+        } else if rest.starts_with('=') {
+            (CompareOp::Eq, 1)
+        } else if rest.starts_with('>') {
+            (CompareOp::Gt, 1)
+        } else if rest.starts_with('<') {
+            (CompareOp::Lt, 1)
+        } else {
+            return Err(invalid());
+        };
+
+        let path = s[..start].trim();
+        if path.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            path: path.to_string(),
+            op,
+            value: rest[len..].to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(json: &str) -> Record {
+        Record::parse_json(json).unwrap()
+    }
+
+    fn matches(predicate: &str, json: &str) -> bool {
+        predicate
+            .parse::<Predicate>()
+            .unwrap()
+            .matches(&record(json))
+    }
+
+    #[test]
+    fn test_parse_json_records() {
+        assert!(Record::parse_json(r#"{"level":"info"}"#).is_some());
+        assert!(Record::parse_json("[1, 2]").is_none());
+        assert!(Record::parse_json("2025-10-03 plain text").is_none());
+    }
+
+    #[test]
+    fn test_parse_logfmt_records() {
+        let record = Record::parse_logfmt(
+            r#"ts=2025-10-03T12:00:00Z level=warn msg="disk \"sda\" = full" dur=12ms cached empty="#,
+        )
+        .unwrap();
+        assert_eq!(
+            record,
+            Record::Logfmt(vec![
+                ("ts".to_string(), "2025-10-03T12:00:00Z".to_string()),
+                ("level".to_string(), "warn".to_string()),
+                ("msg".to_string(), r#"disk "sda" = full"#.to_string()),
+                ("dur".to_string(), "12ms".to_string()),
+                ("cached".to_string(), "true".to_string()),
+                ("empty".to_string(), String::new()),
+            ])
+        );
+        assert_eq!(record.field("level").unwrap().as_text(), "warn");
+        assert!(Record::parse_logfmt("plain text without pairs").is_none());
+        assert!(Record::parse_logfmt("a = b").is_none());
+    }
+
+    #[test]
+    fn test_logfmt_predicates() {
+        let record = Record::parse_logfmt(r#"level=warn status=503 msg="slow query""#).unwrap();
+        let check = |predicate: &str| predicate.parse::<Predicate>().unwrap().matches(&record);
+        assert!(check("level=warn"));
+        assert!(check("status>=500"));
+        assert!(check("msg=slow query"));
+        assert!(!check("level=error"));
+    }
+
+    #[test]
+    fn test_field_paths() {
+        let record = record(
+            r#"{"@timestamp":"2025-10-03T12:00:00Z","request":{"start":1759492800,"tags":["a","b"]},"k8s.pod":"web-1","ok":true,"gone":null}"#,
+        );
+        assert_eq!(
+            record.field("@timestamp"),
+            Some(FieldValue::Text(Cow::Borrowed("2025-10-03T12:00:00Z")))
+        );
+        assert_eq!(
+            record.field("request.start"),
+            Some(FieldValue::Number(1759492800.0))
+        );
+        assert_eq!(
+            record.field("request.tags.1"),
+            Some(FieldValue::Text(Cow::Borrowed("b")))
+        );
+        assert_eq!(
+            record.field("k8s.pod"),
+            Some(FieldValue::Text(Cow::Borrowed("web-1")))
+        );
+        assert_eq!(record.field("ok").unwrap().as_text(), "true");
+        assert_eq!(record.field("gone"), None);
+        assert_eq!(record.field("request.missing"), None);
+    }
+
+    #[test]
+    fn test_predicates() {
+        let line = r#"{"level":"error","status":503,"code":"404","user":{"id":7}}"#;
+        assert!(matches("level=error", line));
+        assert!(!matches("level=warn", line));
+        assert!(matches("level!=warn", line));
+        assert!(matches("status>=500", line));
+        assert!(!matches("status<500", line));
+        assert!(matches("status==503.0", line));
+        assert!(matches("code>400", line));
+        assert!(matches("user.id=7", line));
+        // Missing fields only satisfy !=
+        assert!(!matches("region=eu", line));
+        assert!(matches("region!=eu", line));
+    }
+
+    #[test]
+    fn test_invalid_predicates() {
+        for predicate in ["level", "=error", "  >=5"] {
+            assert!(predicate.parse::<Predicate>().is_err(), "{predicate}");
+        }
+    }
+}
@@ -1,5 +1,9 @@
-use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use crate::structured::FieldValue;
+use crate::timezone::{local_to_utc, DstPolicy};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use regex::Regex;
+use std::fmt;
 
 /// Common timestamp formats to auto-detect
 const COMMON_FORMATS: &[&str] = &[
@@ -33,6 +37,147 @@ const COMMON_FORMATS: &[&str] = &[
     "%a, %d %b %Y %H:%M:%S",
 ];
 
+/// Where a timestamp candidate is taken from in a line; detection tries
+/// them in this order
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Extractor {
+    /// An epoch number at the start of the line
+    Unix,
+    Iso,
+    Apache,
+    Rfc2822,
+    DateTime,
+    Syslog,
+    YearlessIso,
+    /// An epoch number given as a time field, as in `ts=…` or `"time": …`
+    KeyedEpoch,
+    TimeOnly,
+    /// An epoch number anywhere in the line. IDs and phone numbers look the
+    /// same, so this is only tried once everything else has missed.
+    Epoch,
+    /// The first 50 characters, when no regex finds anything
+    Prefix,
+}
+
+const EXTRACTORS: &[Extractor] = &[
+    Extractor::Unix,
+    Extractor::Iso,
+    Extractor::Apache,
+    Extractor::Rfc2822,
+    Extractor::DateTime,
+    Extractor::Syslog,
+    Extractor::YearlessIso,
+    Extractor::KeyedEpoch,
+    Extractor::TimeOnly,
+    Extractor::Epoch,
+];
+
+/// Format under which detected epoch numbers are cached: the unit is
+/// inferred from each number's magnitude
+const EPOCH_FORMAT: &str = "epoch";
+
+/// Detected epoch numbers before 2000-01-01 are taken to be something else
+const EARLIEST_EPOCH: i64 = 946_684_800;
+
+/// Unit of an epoch number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EpochUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl EpochUnit {
+    /// The unit named by a `--time-format` value: `epoch_s`, `epoch_ms`,
+    /// `epoch_us` or `epoch_ns`. Plain `epoch` gives `Some(None)`, for a unit
+    /// inferred from each number's magnitude.
+    pub fn from_format(format: &str) -> Option<Option<Self>> {
+        match format {
+            EPOCH_FORMAT => Some(None),
+            "epoch_s" => Some(Some(EpochUnit::Seconds)),
+            "epoch_ms" => Some(Some(EpochUnit::Millis)),
+            "epoch_us" => Some(Some(EpochUnit::Micros)),
+            "epoch_ns" => Some(Some(EpochUnit::Nanos)),
+            _ => None,
+        }
+    }
+
+    /// The unit of an epoch number with `digits` integer digits, with the
+    /// same bounds as `parse_epoch`
+    fn from_digits(digits: usize) -> Self {
+        match digits {
+            0..=11 => EpochUnit::Seconds,
+            12..=14 => EpochUnit::Millis,
+            15..=17 => EpochUnit::Micros,
+            _ => EpochUnit::Nanos,
+        }
+    }
+
+    fn nanos(self) -> i64 {
+        match self {
+            EpochUnit::Seconds => 1_000_000_000,
+            EpochUnit::Millis => 1_000_000,
+            EpochUnit::Micros => 1_000,
+            EpochUnit::Nanos => 1,
+        }
+    }
+}
+
+impl fmt::Display for EpochUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EpochUnit::Seconds => "epoch_s",
+            EpochUnit::Millis => "epoch_ms",
+            EpochUnit::Micros => "epoch_us",
+            EpochUnit::Nanos => "epoch_ns",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The extractor and format that parsed a line, cached per source so that
+/// later lines, which almost always use the same format, skip detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectedFormat {
+    extractor: Extractor,
+    format: &'static str,
+    /// Unit of the epoch number that was detected, for reporting
+    epoch_unit: Option<EpochUnit>,
+}
+
+impl fmt::Display for DetectedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.epoch_unit {
+            Some(unit) => write!(f, "{}", unit),
+            None => write!(f, "{}", self.format),
+        }
+    }
+}
+
+/// What the parser learns from a source's lines that helps with the next
+#[derive(Debug, Clone, Default)]
+pub struct ParseContext {
+    /// The format that parsed the last timestamp, tried first for the next
+    pub format: Option<DetectedFormat>,
+    /// When the source was last written, the default reference date for
+    /// timestamps without a year
+    modified: Option<DateTime<Utc>>,
+    /// The last timestamp without a year, in the input zone
+    previous_yearless: Option<NaiveDateTime>,
+}
+
+impl ParseContext {
+    /// Context for a source last modified at `modified`; None (stdin, or a
+    /// followed file that keeps growing) makes now the reference date
+    pub fn new(modified: Option<DateTime<Utc>>) -> Self {
+        Self {
+            modified,
+            ..Self::default()
+        }
+    }
+}
+
 /// Timestamp parser with auto-detection capabilities
 pub struct TimestampParser {
     custom_format: Option<String>,
@@ -43,8 +188,20 @@ pub struct TimestampParser {
     apache_regex: Regex,
     rfc2822_regex: Regex,
     unix_timestamp_regex: Regex,
+    keyed_epoch_regex: Regex,
+    epoch_regex: Regex,
+    number_regex: Regex,
     yearless_iso_regex: Regex,
     time_only_regex: Regex,
+    // Value of a `name=value` or `"name": value` field the timestamp is
+    // taken from, instead of the whole line
+    field_regex: Option<Regex>,
+    // Zone for timestamps that carry no offset of their own
+    input_tz: Tz,
+    dst_policy: DstPolicy,
+    // Timestamps without a year are placed at or before this date (or the
+    // source's modification time, or now)
+    reference_date: Option<DateTime<Utc>>,
 }
 
 impl TimestampParser {
@@ -68,30 +225,137 @@ impl TimestampParser {
                 r"[A-Z][a-z]{2},\s+\d{2}\s+[A-Z][a-z]{2}\s+\d{4}\s+\d{2}:\d{2}:\d{2}",
             )
             .unwrap(),
-            unix_timestamp_regex: Regex::new(r"^\d{10}(?:\.\d+)?").unwrap(),
+            // 10, 13, 16 or 19 digits: seconds, ms, µs or ns since 2001
+            unix_timestamp_regex: Regex::new(r"^(?:\d{19}|\d{16}|\d{13}|\d{10})(?:\.\d+)?\b")
+                .unwrap(),
+            keyed_epoch_regex: Regex::new(
+                r#"(?i)(?:^|[^\w.@])"?@?(?:ts|time|timestamp|epoch)(?:_(?:s|ms|us|ns|millis|micros|nanos))?"?\s*[=:]\s*"?((?:\d{19}|\d{16}|\d{13}|\d{10})(?:\.\d+)?)(?:[^\w.]|$)"#,
+            )
+            .unwrap(),
+            epoch_regex: Regex::new(
+                r"(?:^|[^\w.])((?:\d{19}|\d{16}|\d{13}|\d{10})(?:\.\d+)?)(?:[^\w.]|$)",
+            )
+            .unwrap(),
+            number_regex: Regex::new(r"(?:^|[^\w.])(\d+(?:\.\d+)?)(?:[^\w.]|$)").unwrap(),
             yearless_iso_regex: Regex::new(r"\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z)?")
                 .unwrap(),
             time_only_regex: Regex::new(r"\d{2}:\d{2}:\d{2}(?:\.\d+)?").unwrap(),
+            field_regex: None,
+            input_tz: Tz::UTC,
+            dst_policy: DstPolicy::default(),
+            reference_date: None,
         }
     }
 
+    /// Interpret timestamps without an offset as wall-clock time in `tz`,
+    /// resolving DST ambiguities and gaps according to `dst_policy`
+    pub fn with_timezone(mut self, tz: Tz, dst_policy: DstPolicy) -> Self {
+        self.input_tz = tz;
+        self.dst_policy = dst_policy;
+        self
+    }
+
+    /// Give timestamps without a year the latest year that does not put them
+    /// more than a day after `reference`, instead of each source's
+    /// modification time or now
+    pub fn with_reference_date(mut self, reference: DateTime<Utc>) -> Self {
+        self.reference_date = Some(reference);
+        self
+    }
+
+    /// Take timestamps from the value of the `name` field of each line,
+    /// written `name=value`, `name: value` or `"name": "value"`, rather
+    /// than from anywhere in the line
+    pub fn with_field(mut self, name: &str) -> Self {
+        let pattern = format!(
+            r#"(?:^|[^\w.])"?{}"?\s*[=:]\s*(?:"([^"]*)"|([^\s,;}}\]]+))"#,
+            regex::escape(name)
+        );
+        self.field_regex = Some(Regex::new(&pattern).unwrap());
+        self
+    }
+
     /// Extract and parse timestamp from a log line
     pub fn parse_line(&self, line: &str) -> Option<DateTime<Utc>> {
+        self.parse_line_cached(line, &mut ParseContext::default())
+    }
+
+    /// Like `parse_line`, for a line from a source whose earlier lines were
+    /// parsed with `context`: the extractor and format that parsed the last
+    /// of them are tried first, and only if they miss does the line go
+    /// through full detection, whose result replaces `context.format`.
+    /// Timestamps without a year follow on from the previous one, so a
+    /// source crossing New Year's Eve rolls over into the next year.
+    pub fn parse_line_cached(
+        &self,
+        line: &str,
+        context: &mut ParseContext,
+    ) -> Option<DateTime<Utc>> {
+        match self.field_regex {
+            Some(ref field) => {
+                let captures = field.captures(line)?;
+                let value = captures.get(1).or_else(|| captures.get(2))?;
+                self.parse_text(value.as_str(), context)
+            }
+            None => self.parse_text(line, context),
+        }
+    }
+
+    fn parse_text(&self, line: &str, context: &mut ParseContext) -> Option<DateTime<Utc>> {
         // Try custom format first if provided
         if let Some(ref fmt) = self.custom_format {
-            if let Some(ts) = self.parse_with_format(line, fmt) {
-                return Some(ts);
+            let parsed = match EpochUnit::from_format(fmt) {
+                // An epoch format takes the first number in the line
+                Some(unit) => self
+                    .number_regex
+                    .captures(line)
+                    .and_then(|captures| parse_epoch_text(&captures[1], unit))
+                    .map(Candidate::Parsed),
+                None => self.parse_with_format(line, fmt, context),
+            };
+            if let Some(parsed) = parsed {
+                return parsed.into_timestamp();
+            }
+        }
+
+        // A bare number in the middle of the line is never taken ahead of
+        // the other extractors, so it goes through detection every time
+        let cached = context
+            .format
+            .filter(|detected| detected.extractor != Extractor::Epoch);
+        if let Some(detected) = cached {
+            if let Some(candidate) = self.find_candidate(detected.extractor, line) {
+                if let Some(parsed) = self.parse_with_format(candidate, detected.format, context) {
+                    // The epoch format covers every unit
+                    if detected.epoch_unit.is_some() {
+                        context.format = Some(DetectedFormat {
+                            epoch_unit: epoch_digits(candidate).map(EpochUnit::from_digits),
+                            ..detected
+                        });
+                    }
+                    return parsed.into_timestamp();
+                }
             }
         }
 
         // Try to extract timestamp-like strings using regex
         let candidates = self.extract_timestamp_candidates(line);
 
-        for candidate in candidates {
-            // Try each common format
-            for format in COMMON_FORMATS {
-                if let Some(ts) = self.parse_with_format(&candidate, format) {
-                    return Some(ts);
+        for (extractor, candidate) in candidates {
+            // Epoch numbers parse whatever the format
+            let epoch_unit = epoch_digits(candidate).map(EpochUnit::from_digits);
+            let formats = match epoch_unit {
+                Some(_) => &[EPOCH_FORMAT],
+                None => COMMON_FORMATS,
+            };
+            for format in formats {
+                if let Some(parsed) = self.parse_with_format(candidate, format, context) {
+                    context.format = Some(DetectedFormat {
+                        extractor,
+                        format,
+                        epoch_unit,
+                    });
+                    return parsed.into_timestamp();
                 }
             }
         }
@@ -99,98 +363,141 @@ impl TimestampParser {
         None
     }
 
-    fn extract_timestamp_candidates(&self, line: &str) -> Vec<String> {
-        let mut candidates = Vec::new();
-
-        // Try Unix timestamp first (at start of line only)
-        if let Some(mat) = self.unix_timestamp_regex.find(line) {
-            if mat.start() < 5 {
-                // Must be near start of line
-                candidates.push(mat.as_str().to_string());
+    /// Whether a line begins with a timestamp (optionally after a `[`), which
+    /// marks the start of a new record in multiline mode
+    pub fn starts_with_timestamp(&self, line: &str) -> bool {
+        if let Some(ref fmt) = self.custom_format {
+            if EpochUnit::from_format(fmt).is_some() {
+                let line = line.strip_prefix('[').unwrap_or(line);
+                return line.starts_with(|c: char| c.is_ascii_digit());
             }
+            return NaiveDateTime::parse_and_remainder(line, fmt).is_ok()
+                || DateTime::parse_and_remainder(line, fmt).is_ok();
         }
 
-        // Try ISO format
-        if let Some(mat) = self.iso_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
-        }
-
-        // Try Apache/Nginx format
-        if let Some(mat) = self.apache_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
-        }
-
-        // Try RFC 2822 format
-        if let Some(mat) = self.rfc2822_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
-        }
+        let offset = if line.starts_with('[') { 1 } else { 0 };
+        [
+            &self.unix_timestamp_regex,
+            &self.iso_regex,
+            &self.apache_regex,
+            &self.rfc2822_regex,
+            &self.datetime_regex,
+            &self.syslog_regex,
+            &self.yearless_iso_regex,
+            &self.time_only_regex,
+        ]
+        .iter()
+        .filter_map(|regex| regex.find(line))
+        .filter(|mat| mat.start() <= offset)
+        .any(|mat| {
+            COMMON_FORMATS.iter().any(|format| {
+                self.parse_with_format(mat.as_str(), format, &mut ParseContext::default())
+                    .is_some()
+            })
+        })
+    }
 
-        // Try datetime format (EU/US dates)
-        if let Some(mat) = self.datetime_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
-        }
+    /// Parse a timestamp taken from a structured log field. Numbers, and
+    /// text that is a number, are epoch times; other text is parsed like a line.
+    pub fn parse_field(&self, value: &FieldValue) -> Option<DateTime<Utc>> {
+        self.parse_field_cached(value, &mut ParseContext::default())
+    }
 
-        // Try syslog format
-        if let Some(mat) = self.syslog_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
+    /// `parse_field` with the per-source context of `parse_line_cached`
+    pub fn parse_field_cached(
+        &self,
+        value: &FieldValue,
+        context: &mut ParseContext,
+    ) -> Option<DateTime<Utc>> {
+        match value.as_number() {
+            Some(epoch) => parse_epoch(epoch),
+            None => self.parse_text(&value.as_text(), context),
         }
+    }
 
-        // Try yearless ISO format
-        if let Some(mat) = self.yearless_iso_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
-        }
+    /// Timestamp-like text found in a line by one extractor
+    fn find_candidate<'a>(&self, extractor: Extractor, line: &'a str) -> Option<&'a str> {
+        let regex = match extractor {
+            // Unix timestamps only count near the start of the line
+            Extractor::Unix => {
+                let mat = self.unix_timestamp_regex.find(line)?;
+                return (mat.start() < 5).then_some(mat.as_str());
+            }
+            Extractor::KeyedEpoch => {
+                let captures = self.keyed_epoch_regex.captures(line)?;
+                return captures.get(1).map(|mat| mat.as_str());
+            }
+            Extractor::Epoch => {
+                let captures = self.epoch_regex.captures(line)?;
+                return captures.get(1).map(|mat| mat.as_str());
+            }
+            Extractor::Prefix => return (line.len() >= 10).then(|| &line[..line.len().min(50)]),
+            Extractor::Iso => &self.iso_regex,
+            Extractor::Apache => &self.apache_regex,
+            Extractor::Rfc2822 => &self.rfc2822_regex,
+            Extractor::DateTime => &self.datetime_regex,
+            Extractor::Syslog => &self.syslog_regex,
+            Extractor::YearlessIso => &self.yearless_iso_regex,
+            Extractor::TimeOnly => &self.time_only_regex,
+        };
+        regex.find(line).map(|mat| mat.as_str())
+    }
 
-        // Try time-only format
-        if let Some(mat) = self.time_only_regex.find(line) {
-            candidates.push(mat.as_str().to_string());
-        }
+    fn extract_timestamp_candidates<'a>(&self, line: &'a str) -> Vec<(Extractor, &'a str)> {
+        let mut candidates: Vec<_> = EXTRACTORS
+            .iter()
+            .filter_map(|&extractor| Some((extractor, self.find_candidate(extractor, line)?)))
+            .collect();
 
         // Also try the first 50 chars as a fallback
-        if candidates.is_empty() && line.len() >= 10 {
-            candidates.push(line[..line.len().min(50)].to_string());
+        if candidates.is_empty() {
+            candidates.extend(
+                self.find_candidate(Extractor::Prefix, line)
+                    .map(|prefix| (Extractor::Prefix, prefix)),
+            );
         }
 
         candidates
     }
 
-    fn parse_with_format(&self, text: &str, format: &str) -> Option<DateTime<Utc>> {
+    fn parse_with_format(
+        &self,
+        text: &str,
+        format: &str,
+        context: &mut ParseContext,
+    ) -> Option<Candidate> {
         let trimmed = text.trim();
 
+        if let Some(unit) = EpochUnit::from_format(format) {
+            return parse_epoch_text(trimmed, unit).map(Candidate::Parsed);
+        }
+
         // Try parsing Unix timestamp
-        if let Ok(unix_ts) = trimmed.parse::<i64>() {
-            if unix_ts > 1000000000 && unix_ts < 9999999999 {
-                // Reasonable timestamp range
-                return DateTime::from_timestamp(unix_ts, 0);
-            }
+        if let Some(digits) = epoch_digits(trimmed) {
+            return parse_epoch_text(trimmed, Some(EpochUnit::from_digits(digits)))
+                .map(Candidate::Parsed);
         }
 
         // Try parsing as DateTime with timezone
         if let Ok(dt) = DateTime::parse_from_str(trimmed, format) {
-            return Some(dt.with_timezone(&Utc));
+            return Some(Candidate::Parsed(dt.with_timezone(&Utc)));
         }
 
         // Try parsing as NaiveDateTime (no timezone)
         if let Ok(ndt) = NaiveDateTime::parse_from_str(trimmed, format) {
-            return Some(DateTime::from_naive_utc_and_offset(ndt, Utc));
-        }
-
-        // For syslog format, we need to add the year
-        if format.contains("%b") && !format.contains("%Y") {
-            let current_year = Utc::now().year();
-            let with_year = format!("{} {}", current_year, trimmed);
-            let format_with_year = format!("%Y {}", format);
-            if let Ok(ndt) = NaiveDateTime::parse_from_str(&with_year, &format_with_year) {
-                return Some(DateTime::from_naive_utc_and_offset(ndt, Utc));
-            }
+            return Some(self.naive_to_utc(ndt, format));
         }
 
-        // For yearless ISO formats, we need to add the year
-        if format.starts_with("%m-") && !format.contains("%Y") {
-            let current_year = Utc::now().year();
-            let with_year = format!("{}-{}", current_year, trimmed);
-            let format_with_year = format!("%Y-{}", format);
+        // For syslog and yearless ISO formats, parse in a leap year, so
+        // that Feb 29 is accepted, then work out the real year
+        let syslog = format.contains("%b");
+        if !format.contains("%Y") && (syslog || format.starts_with("%m-")) {
+            let separator = if syslog { " " } else { "-" };
+            let with_year = format!("2000{}{}", separator, trimmed);
+            let format_with_year = format!("%Y{}{}", separator, format);
             if let Ok(ndt) = NaiveDateTime::parse_from_str(&with_year, &format_with_year) {
-                return Some(DateTime::from_naive_utc_and_offset(ndt, Utc));
+                let ndt = self.infer_year(ndt, context)?;
+                return Some(self.naive_to_utc(ndt, format));
             }
         }
 
@@ -200,23 +507,143 @@ impl TimestampParser {
             && !format.contains("%m")
             && !format.contains("%d")
         {
-            let now = Utc::now();
-            let current_date = now.date_naive();
+            let current_date = Utc::now().with_timezone(&self.input_tz).date_naive();
             let with_date = format!("{} {}", current_date.format("%Y-%m-%d"), trimmed);
             let format_with_date = format!("%Y-%m-%d {}", format);
             if let Ok(ndt) = NaiveDateTime::parse_from_str(&with_date, &format_with_date) {
-                return Some(DateTime::from_naive_utc_and_offset(ndt, Utc));
+                return Some(self.naive_to_utc(ndt, format));
             }
         }
 
         None
     }
+
+    /// Give a timestamp written without a year the year that puts it nearest
+    /// the source's previous one, so that the month going backwards (Dec to
+    /// Jan) rolls over into the next year. The first is given the latest year
+    /// that does not put it more than a day after the reference date.
+    fn infer_year(
+        &self,
+        undated: NaiveDateTime,
+        context: &mut ParseContext,
+    ) -> Option<NaiveDateTime> {
+        let nearest = context.previous_yearless.and_then(|previous| {
+            (previous.year() - 1..=previous.year() + 1)
+                .filter_map(|year| undated.with_year(year))
+                .min_by_key(|ts| (*ts - previous).abs())
+        });
+        let inferred = nearest.or_else(|| {
+            let reference = self
+                .reference_date
+                .or(context.modified)
+                .unwrap_or_else(Utc::now);
+            // A day of slack, for clocks running ahead and for a reference
+            // given as a date, which means its midnight
+            let limit = reference.with_timezone(&self.input_tz).naive_local() + Duration::days(1);
+            // Feb 29 may need to go back to the last leap year
+            (limit.year() - 4..=limit.year())
+                .rev()
+                .filter_map(|year| undated.with_year(year))
+                .find(|ts| *ts < limit)
+        })?;
+        context.previous_yearless = Some(inferred);
+        Some(inferred)
+    }
+
+    fn naive_to_utc(&self, ndt: NaiveDateTime, format: &str) -> Candidate {
+        // A literal trailing Z is a UTC designator, not a local time
+        if format.ends_with('Z') {
+            return Candidate::Parsed(ndt.and_utc());
+        }
+        match local_to_utc(&self.input_tz, ndt, self.dst_policy) {
+            Some(ts) => Candidate::Parsed(ts),
+            None => Candidate::Rejected,
+        }
+    }
+}
+
+/// Convert an epoch time to UTC, inferring the unit from its magnitude:
+/// seconds, milliseconds, microseconds or nanoseconds
+pub fn parse_epoch(epoch: f64) -> Option<DateTime<Utc>> {
+    if !epoch.is_finite() || epoch < 0.0 {
+        return None;
+    }
+    let micros = if epoch < 1e11 {
+        epoch * 1e6
+    } else if epoch < 1e14 {
+        epoch * 1e3
+    } else if epoch < 1e17 {
+        epoch
+    } else {
+        epoch / 1e3
+    };
+    DateTime::from_timestamp_micros(micros.round() as i64)
+}
+
+/// Convert an epoch number written as text, such as `1727962496`,
+/// `1727962496123` or `1727962496.123`, to UTC exactly. The unit is `unit`,
+/// or inferred from the number of integer digits if it is None.
+pub fn parse_epoch_text(text: &str, unit: Option<EpochUnit>) -> Option<DateTime<Utc>> {
+    let (int, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty() || !is_digits(int) || !is_digits(fraction) {
+        return None;
+    }
+    let unit = unit.unwrap_or_else(|| EpochUnit::from_digits(int.len()));
+
+    // Digits finer than a nanosecond are dropped
+    let fraction = format!("{:0<9.9}", fraction);
+    let fraction_nanos = fraction.parse::<i64>().ok()? * unit.nanos() / 1_000_000_000;
+    let nanos = int
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(unit.nanos())?
+        .checked_add(fraction_nanos)?;
+    Some(DateTime::from_timestamp_nanos(nanos))
+}
+
+/// The number of integer digits of text that is plausibly an epoch number:
+/// 10, 13, 16 or 19 digits, for seconds, ms, µs or ns, with an optional
+/// fraction, giving a time from 2000 up to a year from now
+fn epoch_digits(text: &str) -> Option<usize> {
+    let int = text.split_once('.').map_or(text, |(int, _)| int);
+    let digits = int.len();
+    if !matches!(digits, 10 | 13 | 16 | 19) {
+        return None;
+    }
+    let ts = parse_epoch_text(text, Some(EpochUnit::from_digits(digits)))?;
+    let earliest = DateTime::from_timestamp(EARLIEST_EPOCH, 0)?;
+    let plausible = ts >= earliest && ts <= Utc::now() + Duration::days(366);
+    plausible.then_some(digits)
+}
+
+/// Outcome of matching a timestamp candidate against a format
+enum Candidate {
+    Parsed(DateTime<Utc>),
+    /// The text matched but names a local time the DST policy rejects, so
+    /// the line must not fall back to a looser format
+    Rejected,
+}
+
+impl Candidate {
+    fn into_timestamp(self) -> Option<DateTime<Utc>> {
+        match self {
+            Candidate::Parsed(ts) => Some(ts),
+            Candidate::Rejected => None,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Timelike;
+    use chrono::{Duration, TimeZone, Timelike};
+
+    /// Reference date that puts every yearless timestamp in 2025, like
+    /// `--reference-date 2025-12-31`
+    fn end_of_2025() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 12, 31, 0, 0, 0).unwrap()
+    }
 
     #[test]
     fn test_parse_iso8601_with_timezone() {
@@ -372,7 +799,7 @@ mod tests {
     #[test]
     fn test_parse_with_format_unix() {
         let parser = TimestampParser::new(None);
-        let result = parser.parse_with_format("1727962496", "");
+        let result = parser.parse_with_format("1727962496", "", &mut ParseContext::default());
         assert!(result.is_some());
     }
 
@@ -380,11 +807,11 @@ mod tests {
     fn test_parse_with_format_invalid_unix() {
         let parser = TimestampParser::new(None);
         // Too small to be a valid Unix timestamp
-        let result = parser.parse_with_format("123456", "");
+        let result = parser.parse_with_format("123456", "", &mut ParseContext::default());
         assert!(result.is_none());
 
         // Too large
-        let result = parser.parse_with_format("99999999999", "");
+        let result = parser.parse_with_format("99999999999", "", &mut ParseContext::default());
         assert!(result.is_none());
     }
 
@@ -403,44 +830,94 @@ mod tests {
     }
 
     #[test]
-    fn test_syslog_year_injection() {
+    fn test_parse_line_cached_learns_format() {
+        let parser = TimestampParser::new(None);
+        let mut context = ParseContext::default();
+
+        let ts = parser.parse_line_cached("2025-10-03 14:30:45 INFO start", &mut context);
+        assert_eq!(ts, Utc.with_ymd_and_hms(2025, 10, 3, 14, 30, 45).single());
+        let learned = context.format.unwrap();
+        assert_eq!(learned.to_string(), "%Y-%m-%d %H:%M:%S%.f");
+
+        // Later lines parse with the cached format and leave it in place
+        let ts = parser.parse_line_cached("2025-10-03 14:31:00.250 INFO next", &mut context);
+        assert_eq!(ts.unwrap().timestamp_millis() % 1000, 250);
+        assert_eq!(context.format, Some(learned));
+
+        // A miss falls back to full detection and learns the new format
+        let ts = parser.parse_line_cached("Oct 03 14:32:00 host app: INFO", &mut context);
+        assert_eq!(ts.unwrap().minute(), 32);
+        assert_eq!(context.format.unwrap().to_string(), "%b %d %H:%M:%S");
+
+        // Lines without a timestamp keep the cache
+        assert!(parser
+            .parse_line_cached("no timestamp here", &mut context)
+            .is_none());
+        assert_eq!(context.format.unwrap().to_string(), "%b %d %H:%M:%S");
+    }
+
+    #[test]
+    fn test_parse_line_cached_matches_detection() {
         let parser = TimestampParser::new(None);
+        let lines = [
+            "2025-10-03T14:30:45.123Z INFO",
+            "1727962496 INFO",
+            "[03/Oct/2025:14:30:45 +0000] GET /",
+            "03/10/2025 14:30:45 INFO",
+            "Oct 03 14:30:45 host app",
+            "09-24T23:45:29.362Z| INFO",
+        ];
+        for line in lines {
+            let mut context = ParseContext::default();
+            let first = parser.parse_line_cached(line, &mut context);
+            assert!(context.format.is_some(), "{}", line);
+            assert_eq!(first, parser.parse_line(line), "{}", line);
+            assert_eq!(
+                parser.parse_line_cached(line, &mut context),
+                first,
+                "{}",
+                line
+            );
+        }
+
+        let mut context = ParseContext::default();
+        parser.parse_line_cached("1727962496 INFO", &mut context);
+        assert_eq!(context.format.unwrap().to_string(), "epoch_s");
+        parser.parse_line_cached("1727962496123 INFO", &mut context);
+        assert_eq!(context.format.unwrap().to_string(), "epoch_ms");
+    }
+
+    #[test]
+    fn test_syslog_year_injection() {
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
         let line = "Oct 03 14:30:45 myserver app: INFO message";
         let result = parser.parse_line(line);
         assert!(result.is_some());
-
-        // Verify it uses current year
-        let current_year = Utc::now().year();
-        assert_eq!(result.unwrap().year(), current_year);
+        assert_eq!(result.unwrap().year(), 2025);
     }
 
     #[test]
     fn test_parse_yearless_iso() {
-        let parser = TimestampParser::new(Some("%m-%dT%H:%M:%S%.3fZ".to_string()));
+        let parser = TimestampParser::new(Some("%m-%dT%H:%M:%S%.3fZ".to_string()))
+            .with_reference_date(end_of_2025());
         let line = "09-24T23:45:29.362Z| INFO| Some random logline";
         let result = parser.parse_line(line);
         assert!(result.is_some());
-
-        // Verify it uses current year
-        let current_year = Utc::now().year();
-        assert_eq!(result.unwrap().year(), current_year);
+        assert_eq!(result.unwrap().year(), 2025);
     }
 
     #[test]
     fn test_parse_yearless_iso_auto_detection() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
         let line = "09-24T23:45:29.362Z| INFO| Some random logline";
         let result = parser.parse_line(line);
         assert!(result.is_some());
-
-        // Verify it uses current year
-        let current_year = Utc::now().year();
-        assert_eq!(result.unwrap().year(), current_year);
+        assert_eq!(result.unwrap().year(), 2025);
     }
 
     #[test]
     fn test_parse_yearless_iso_variations() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
 
         // Test different variations of yearless ISO format
         let test_cases = vec![
@@ -464,8 +941,7 @@ mod tests {
             assert!(result.is_some(), "Failed to parse: {}", timestamp);
 
             let dt = result.unwrap();
-            let current_year = Utc::now().year();
-            assert_eq!(dt.year(), current_year);
+            assert_eq!(dt.year(), 2025);
             assert_eq!(dt.month(), expected_month);
             assert_eq!(dt.day(), expected_day);
             assert_eq!(dt.hour(), expected_hour);
@@ -476,14 +952,13 @@ mod tests {
 
     #[test]
     fn test_parse_yearless_iso_without_milliseconds() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
         let line = "09-24T23:45:29Z| INFO| Some random logline";
         let result = parser.parse_line(line);
         assert!(result.is_some());
 
         let dt = result.unwrap();
-        let current_year = Utc::now().year();
-        assert_eq!(dt.year(), current_year);
+        assert_eq!(dt.year(), 2025);
         assert_eq!(dt.month(), 9);
         assert_eq!(dt.day(), 24);
         assert_eq!(dt.hour(), 23);
@@ -493,16 +968,15 @@ mod tests {
 
     #[test]
     fn test_parse_yearless_iso_without_timezone() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
         let line = "09-24T23:45:29.362| INFO| Some random logline";
         let result = parser.parse_line(line);
         // This format is being parsed by the regular ISO regex, not the yearless one
-        // So it should succeed but use the current year
+        // So it should succeed but use the reference year
         assert!(result.is_some());
 
         let dt = result.unwrap();
-        let current_year = Utc::now().year();
-        assert_eq!(dt.year(), current_year);
+        assert_eq!(dt.year(), 2025);
         assert_eq!(dt.month(), 9);
         assert_eq!(dt.day(), 24);
         assert_eq!(dt.hour(), 23);
@@ -512,7 +986,7 @@ mod tests {
 
     #[test]
     fn test_parse_yearless_iso_edge_cases() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
 
         // Test edge cases
         let test_cases = vec![
@@ -532,8 +1006,7 @@ mod tests {
             );
 
             let dt = result.unwrap();
-            let current_year = Utc::now().year();
-            assert_eq!(dt.year(), current_year, "Wrong year for {}", description);
+            assert_eq!(dt.year(), 2025, "Wrong year for {}", description);
         }
     }
 
@@ -561,7 +1034,7 @@ mod tests {
 
     #[test]
     fn test_parse_yearless_iso_with_different_separators() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
 
         // Test with different log line separators
         let test_cases = vec![
@@ -577,8 +1050,7 @@ mod tests {
             assert!(result.is_some(), "Failed to parse: {}", line);
 
             let dt = result.unwrap();
-            let current_year = Utc::now().year();
-            assert_eq!(dt.year(), current_year);
+            assert_eq!(dt.year(), 2025);
             assert_eq!(dt.month(), 9);
             assert_eq!(dt.day(), 24);
             assert_eq!(dt.hour(), 23);
@@ -589,7 +1061,8 @@ mod tests {
 
     #[test]
     fn test_parse_yearless_iso_with_custom_format() {
-        let parser = TimestampParser::new(Some("%m-%dT%H:%M:%S%.3fZ".to_string()));
+        let parser = TimestampParser::new(Some("%m-%dT%H:%M:%S%.3fZ".to_string()))
+            .with_reference_date(end_of_2025());
 
         let test_cases = vec![
             ("09-24T23:45:29.362Z", 9, 24, 23, 45, 29),
@@ -611,8 +1084,7 @@ mod tests {
             assert!(result.is_some(), "Failed to parse: {}", timestamp);
 
             let dt = result.unwrap();
-            let current_year = Utc::now().year();
-            assert_eq!(dt.year(), current_year);
+            assert_eq!(dt.year(), 2025);
             assert_eq!(dt.month(), expected_month);
             assert_eq!(dt.day(), expected_day);
             assert_eq!(dt.hour(), expected_hour);
@@ -623,7 +1095,7 @@ mod tests {
 
     #[test]
     fn test_parse_yearless_iso_priority() {
-        let parser = TimestampParser::new(None);
+        let parser = TimestampParser::new(None).with_reference_date(end_of_2025());
 
         // Test that yearless ISO format takes priority over other formats
         let line = "09-24T23:45:29.362Z| INFO| This should parse as yearless ISO";
@@ -631,8 +1103,7 @@ mod tests {
         assert!(result.is_some());
 
         let dt = result.unwrap();
-        let current_year = Utc::now().year();
-        assert_eq!(dt.year(), current_year);
+        assert_eq!(dt.year(), 2025);
         assert_eq!(dt.month(), 9);
         assert_eq!(dt.day(), 24);
     }
@@ -750,4 +1221,297 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_naive_timestamp_in_input_timezone() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        let parser = TimestampParser::new(None).with_timezone(tz, DstPolicy::Earliest);
+
+        // Naive timestamps are BST wall-clock time in summer
+        let ts = parser.parse_line("2025-07-01 12:00:00 INFO up").unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-07-01T11:00:00+00:00");
+
+        // Explicit offsets and Z are left alone
+        let ts = parser.parse_line("2025-07-01T12:00:00Z INFO up").unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-07-01T12:00:00+00:00");
+        let ts = parser
+            .parse_line("2025-07-01T12:00:00+02:00 INFO up")
+            .unwrap();
+        assert_eq!(ts.to_rfc3339(), "2025-07-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn test_dst_policy_for_ambiguous_timestamps() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+        let line = "2025-10-26 01:30:00 INFO repeated hour";
+
+        let earliest = TimestampParser::new(None).with_timezone(tz, DstPolicy::Earliest);
+        assert_eq!(
+            earliest.parse_line(line).unwrap().to_rfc3339(),
+            "2025-10-26T00:30:00+00:00"
+        );
+        let latest = TimestampParser::new(None).with_timezone(tz, DstPolicy::Latest);
+        assert_eq!(
+            latest.parse_line(line).unwrap().to_rfc3339(),
+            "2025-10-26T01:30:00+00:00"
+        );
+        let reject = TimestampParser::new(None).with_timezone(tz, DstPolicy::Reject);
+        assert!(reject.parse_line(line).is_none());
+    }
+
+    #[test]
+    fn test_parse_epoch_units() {
+        let expected = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        assert_eq!(parse_epoch(1759492800.0), Some(expected));
+        assert_eq!(parse_epoch(1759492800000.0), Some(expected));
+        assert_eq!(parse_epoch(1759492800000000.0), Some(expected));
+        assert_eq!(parse_epoch(1759492800000000000.0), Some(expected));
+        assert_eq!(
+            parse_epoch(1759492800.25),
+            Some(expected + chrono::Duration::milliseconds(250))
+        );
+        assert_eq!(parse_epoch(-1.0), None);
+    }
+
+    #[test]
+    fn test_parse_field() {
+        use std::borrow::Cow;
+
+        let parser = TimestampParser::new(None);
+        let expected = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        assert_eq!(
+            parser.parse_field(&FieldValue::Number(1759492800123.0)),
+            Some(expected + chrono::Duration::milliseconds(123))
+        );
+        assert_eq!(
+            parser.parse_field(&FieldValue::Text(Cow::Borrowed("1759492800"))),
+            Some(expected)
+        );
+        assert_eq!(
+            parser.parse_field(&FieldValue::Text(Cow::Borrowed("2025-10-03T12:00:00Z"))),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn test_year_rollover() {
+        let reference = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        let parser = TimestampParser::new(None).with_reference_date(reference);
+        let mut context = ParseContext::default();
+        let lines = [
+            ("Dec 30 23:59:00 host a", 2025),
+            ("Dec 31 23:59:59 host b", 2025),
+            ("Jan  1 00:00:01 host c", 2026),
+            // A late line goes back to the previous year without undoing
+            // the rollover
+            ("Dec 31 23:59:58 host d", 2025),
+            ("Jan  4 10:00:00 host e", 2026),
+        ];
+        for (line, year) in lines {
+            let ts = parser.parse_line_cached(line, &mut context).unwrap();
+            assert_eq!(ts.year(), year, "{}", line);
+        }
+
+        let mut context = ParseContext::default();
+        let ts = parser.parse_line_cached("12-31T23:00:00Z app", &mut context);
+        assert_eq!(ts.unwrap().year(), 2025);
+        let ts = parser.parse_line_cached("01-01T01:00:00Z app", &mut context);
+        assert_eq!(ts.unwrap().year(), 2026);
+    }
+
+    #[test]
+    fn test_year_reference_date() {
+        let january = Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap();
+        let parser = TimestampParser::new(None);
+
+        // A December log last written in January is from the previous year
+        let mut context = ParseContext::new(Some(january));
+        let ts = parser.parse_line_cached("Dec 31 12:00:00 host up", &mut context);
+        assert_eq!(ts.unwrap().year(), 2025);
+        let mut context = ParseContext::new(Some(january));
+        let ts = parser.parse_line_cached("Jan  5 12:00:00 host up", &mut context);
+        assert_eq!(ts.unwrap().year(), 2026);
+
+        // --reference-date takes precedence over the modification time
+        let reference = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let parser = parser.with_reference_date(reference);
+        let mut context = ParseContext::new(Some(january));
+        let ts = parser.parse_line_cached("Oct  3 12:00:00 host up", &mut context);
+        assert_eq!(ts.unwrap().year(), 2023);
+
+        // Feb 29 only exists in leap years
+        let ts = parser.parse_line("Feb 29 12:00:00 host up");
+        assert_eq!(ts.unwrap().year(), 2024);
+        let reference = Utc.with_ymd_and_hms(2027, 6, 1, 0, 0, 0).unwrap();
+        let parser = TimestampParser::new(None).with_reference_date(reference);
+        assert_eq!(
+            parser.parse_line("Feb 29 12:00:00 host up").unwrap().year(),
+            2024
+        );
+    }
+
+    #[test]
+    fn test_parse_epoch_in_line() {
+        let parser = TimestampParser::new(None);
+        let expected = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let cases = [
+            ("1759492800 INFO up", expected),
+            (
+                "1759492800123 INFO up",
+                expected + Duration::milliseconds(123),
+            ),
+            (
+                "1759492800123456 INFO up",
+                expected + Duration::microseconds(123456),
+            ),
+            (
+                "1759492800123456789 INFO up",
+                expected + Duration::nanoseconds(123456789),
+            ),
+            (
+                "1759492800.5 INFO up",
+                expected + Duration::milliseconds(500),
+            ),
+            (
+                "1759492800123.5 INFO up",
+                expected + Duration::microseconds(123500),
+            ),
+            // After a time key
+            (
+                "level=info ts=1759492800123 msg=up",
+                expected + Duration::milliseconds(123),
+            ),
+            (r#"{"msg": "up", "time": 1759492800}"#, expected),
+            (r#"{"@timestamp": "1759492800", "msg": "up"}"#, expected),
+            ("up epoch_ms=1759492800000", expected),
+            // Anywhere else, when there is nothing better
+            ("job 1759492800 done", expected),
+        ];
+        for (line, ts) in cases {
+            assert_eq!(parser.parse_line(line), Some(ts), "{}", line);
+        }
+
+        // Other lengths, and digits inside words or decimals, are not epochs
+        assert!(parser.parse_line("order 17594928001 shipped").is_none());
+        assert!(parser.parse_line("order id1759492800 shipped").is_none());
+        assert!(parser.parse_line("ratio 0.1759492800 ok").is_none());
+        // Textual timestamps are preferred
+        let ts = parser.parse_line("2025-10-03 09:00:00 job 1759492800 done");
+        assert_eq!(ts.unwrap().hour(), 9);
+    }
+
+    #[test]
+    fn test_epoch_like_numbers_next_to_time_only() {
+        let parser = TimestampParser::new(None);
+        let time_of_day = |ts: Option<DateTime<Utc>>| {
+            let ts = ts.unwrap();
+            (ts.hour(), ts.minute(), ts.second())
+        };
+
+        // IDs, order and phone numbers are not taken over the time of day
+        let lines = [
+            ("12:00:01 ERROR trace=ab12 id=1234567890123456 failed", 1),
+            ("12:00:02 ERROR order 5551234567 failed", 2),
+            ("12:00:03 WARN call from 4155551234 dropped", 3),
+            ("12:00:04 INFO user 1700000000 logged in", 4),
+        ];
+        for (line, second) in lines {
+            assert_eq!(
+                time_of_day(parser.parse_line(line)),
+                (12, 0, second),
+                "{}",
+                line
+            );
+        }
+
+        // Not even once a bare number was taken from an earlier line
+        let mut context = ParseContext::default();
+        assert!(parser
+            .parse_line_cached("job 1759492800 done", &mut context)
+            .is_some());
+        let ts = parser.parse_line_cached("12:00:01 ERROR id=1234567890123456", &mut context);
+        assert_eq!(time_of_day(ts), (12, 0, 1));
+
+        // Numbers giving times before 2000 or over a year ahead are not epochs
+        assert!(parser.parse_line("order 5551234567 failed").is_none());
+        assert!(parser.parse_line("5551234567 called").is_none());
+        assert!(parser.parse_line("ticket 0912345678 closed").is_none());
+        assert!(parser.parse_line("ts=0123456789123 up").is_none());
+    }
+
+    #[test]
+    fn test_epoch_time_format() {
+        let expected = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let parse = |format: &str, line: &str| {
+            TimestampParser::new(Some(format.to_string())).parse_line(line)
+        };
+        assert_eq!(
+            parse("epoch_ms", "[1759492800123] up"),
+            Some(expected + Duration::milliseconds(123))
+        );
+        assert_eq!(parse("epoch_s", "took 1759492800.25s"), None);
+        assert_eq!(
+            parse("epoch_s", "at 1759492800.25 up"),
+            Some(expected + Duration::milliseconds(250))
+        );
+        // Explicit units accept any number of digits
+        assert_eq!(parse("epoch_ms", "175949280012 up").unwrap().year(), 1975);
+        assert_eq!(
+            parse("epoch_us", "1759492800123456 up"),
+            Some(expected + Duration::microseconds(123456))
+        );
+        assert_eq!(
+            parse("epoch", "1759492800123456789 up"),
+            Some(expected + Duration::nanoseconds(123456789))
+        );
+
+        let parser = TimestampParser::new(Some("epoch_ms".to_string()));
+        assert!(parser.starts_with_timestamp("[1759492800123] up"));
+        assert!(!parser.starts_with_timestamp("\tat Foo.java:42"));
+    }
+
+    #[test]
+    fn test_parse_field_in_line() {
+        let expected = Utc.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+        let parser = TimestampParser::new(None).with_field("ts");
+        assert_eq!(
+            parser.parse_line("id=1700000000 ts=1759492800123 msg=up"),
+            Some(expected + Duration::milliseconds(123))
+        );
+        assert_eq!(
+            parser.parse_line(r#"{"id": 1700000000, "ts": "2025-10-03T12:00:00Z"}"#),
+            Some(expected)
+        );
+        assert_eq!(
+            parser.parse_line("2025-10-01 ts: 1759492800 up"),
+            Some(expected)
+        );
+        // Only the field counts
+        assert!(parser
+            .parse_line("2025-10-03 12:00:00 start=1759492800")
+            .is_none());
+        assert!(parser.parse_line("ts=soon").is_none());
+
+        let parser = TimestampParser::new(Some("epoch_ms".to_string())).with_field("start");
+        assert_eq!(
+            parser.parse_line("took=35 start=1759492800123"),
+            Some(expected + Duration::milliseconds(123))
+        );
+    }
+
+    #[test]
+    fn test_starts_with_timestamp() {
+        let parser = TimestampParser::new(None);
+        assert!(parser.starts_with_timestamp("2025-10-03 12:00:00 ERROR boom"));
+        assert!(parser.starts_with_timestamp("[2025-10-03T12:00:00Z] ERROR boom"));
+        assert!(parser.starts_with_timestamp("[03/Oct/2025:12:00:00 +0000] GET /"));
+        assert!(parser.starts_with_timestamp("Oct  3 12:00:00 host sshd[1]: ok"));
+        assert!(!parser.starts_with_timestamp("\tat com.example.Foo.bar(Foo.java:42)"));
+        assert!(!parser.starts_with_timestamp("Caused by: java.util.concurrent.TimeoutException"));
+        assert!(!parser.starts_with_timestamp("ERROR at 2025-10-03 12:00:00"));
+
+        let parser = TimestampParser::new(Some("%d.%m.%Y %H:%M".to_string()));
+        assert!(parser.starts_with_timestamp("03.10.2025 12:00 boom"));
+        assert!(!parser.starts_with_timestamp("  at line 2"));
+    }
 }
@@ -0,0 +1,120 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// How to resolve local times that occur twice (DST fall back) or not at all
+/// (DST spring forward)
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum DstPolicy {
+    /// Use the earlier of the two possible instants
+    #[default]
+    Earliest,
+    /// Use the later of the two possible instants
+    Latest,
+    /// Treat the timestamp as unparseable
+    Reject,
+}
+
+/// Parse an IANA time zone name such as "Europe/London" or "UTC"
+pub fn parse_timezone(name: &str) -> anyhow::Result<Tz> {
+    name.parse::<Tz>().map_err(|_| {
+        anyhow::anyhow!(
+            "Unknown time zone '{}': expected an IANA name like Europe/London or UTC",
+            name
+        )
+    })
+}
+
+/// Convert a wall-clock time in `tz` to UTC.
+///
+/// Ambiguous times pick one of the two instants according to `policy`. Times
+/// inside a DST gap are interpreted with the offset from after the jump
+/// (earliest) or before it (latest), which yields the two instants the clock
+/// could plausibly have meant.
+pub fn local_to_utc(tz: &Tz, local: NaiveDateTime, policy: DstPolicy) -> Option<DateTime<Utc>> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Some(dt.with_timezone(&Utc)),
+        LocalResult::Ambiguous(earliest, latest) => match policy {
+            DstPolicy::Earliest => Some(earliest.with_timezone(&Utc)),
+            DstPolicy::Latest => Some(latest.with_timezone(&Utc)),
+            DstPolicy::Reject => None,
+        },
+        LocalResult::None => {
+            let offset_before = tz
+                .offset_from_utc_datetime(&(local - Duration::days(1)))
+                .fix()
+                .local_minus_utc();
+            let offset_after = tz
+                .offset_from_utc_datetime(&(local + Duration::days(1)))
+                .fix()
+                .local_minus_utc();
+
+            let earliest = local - Duration::seconds(offset_after as i64);
+            let latest = local - Duration::seconds(offset_before as i64);
+            match policy {
+                DstPolicy::Earliest => Some(earliest.and_utc()),
+                DstPolicy::Latest => Some(latest.and_utc()),
+                DstPolicy::Reject => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn naive(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/London").unwrap(), Tz::Europe__London);
+        assert_eq!(parse_timezone("UTC").unwrap(), Tz::UTC);
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_local_to_utc_single() {
+        let tz = parse_timezone("Europe/London").unwrap();
+        // BST (+01:00) in summer
+        let utc = local_to_utc(&tz, naive(2025, 7, 1, 12, 0), DstPolicy::Earliest).unwrap();
+        assert_eq!(utc, naive(2025, 7, 1, 11, 0).and_utc());
+    }
+
+    #[test]
+    fn test_local_to_utc_ambiguous() {
+        let tz = parse_timezone("Europe/London").unwrap();
+        // 01:30 happens twice on 2025-10-26
+        let local = naive(2025, 10, 26, 1, 30);
+        assert_eq!(
+            local_to_utc(&tz, local, DstPolicy::Earliest).unwrap(),
+            naive(2025, 10, 26, 0, 30).and_utc()
+        );
+        assert_eq!(
+            local_to_utc(&tz, local, DstPolicy::Latest).unwrap(),
+            naive(2025, 10, 26, 1, 30).and_utc()
+        );
+        assert!(local_to_utc(&tz, local, DstPolicy::Reject).is_none());
+    }
+
+    #[test]
+    fn test_local_to_utc_gap() {
+        let tz = parse_timezone("Europe/London").unwrap();
+        // 01:30 does not exist on 2025-03-30
+        let local = naive(2025, 3, 30, 1, 30);
+        assert_eq!(
+            local_to_utc(&tz, local, DstPolicy::Earliest).unwrap(),
+            naive(2025, 3, 30, 0, 30).and_utc()
+        );
+        assert_eq!(
+            local_to_utc(&tz, local, DstPolicy::Latest).unwrap(),
+            naive(2025, 3, 30, 1, 30).and_utc()
+        );
+        assert!(local_to_utc(&tz, local, DstPolicy::Reject).is_none());
+    }
+}
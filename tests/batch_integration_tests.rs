@@ -0,0 +1,784 @@
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+/// Helper to create a temporary log file with content
+fn create_temp_log(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().expect("Failed to create temp file");
+    file.write_all(content.as_bytes())
+        .expect("Failed to write to temp file");
+    file.flush().expect("Failed to flush temp file");
+    file
+}
+
+/// Run logpile to completion and return stdout
+fn run_logpile(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args(args)
+        .output()
+        .expect("Failed to run logpile");
+    assert!(
+        output.status.success(),
+        "logpile failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Run logpile with large files split into chunks of at least
+/// `min_chunk_size` bytes, and return stdout and stderr
+fn run_logpile_chunked(args: &[&str], min_chunk_size: u64) -> (String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args(args)
+        .env("LOGPILE_MIN_CHUNK_SIZE", min_chunk_size.to_string())
+        .output()
+        .expect("Failed to run logpile");
+    assert!(
+        output.status.success(),
+        "logpile failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_parallel_batch_matches_sequential() {
+    let files: Vec<NamedTempFile> = (0..6)
+        .map(|f| {
+            let mut content = String::new();
+            for i in 0..200 {
+                let level = if (i + f) % 3 == 0 { "ERROR" } else { "INFO" };
+                content.push_str(&format!(
+                    "2025-10-03T{:02}:{:02}:{:02}Z {} request {}\n",
+                    10 + f,
+                    i % 60,
+                    (i * 7) % 60,
+                    level,
+                    i
+                ));
+            }
+            create_temp_log(&content)
+        })
+        .collect();
+    let paths: Vec<&str> = files.iter().map(|f| f.path().to_str().unwrap()).collect();
+
+    let mut sequential_args = vec!["ERROR", "-j", "--jobs", "1"];
+    sequential_args.extend(&paths);
+    let mut parallel_args = vec!["ERROR", "-j", "--jobs", "4"];
+    parallel_args.extend(&paths);
+
+    let sequential = run_logpile(&sequential_args);
+    let parallel = run_logpile(&parallel_args);
+
+    assert_eq!(sequential, parallel);
+    let json: serde_json::Value = serde_json::from_str(&parallel).unwrap();
+    assert_eq!(json["total_matches"], 400);
+}
+
+#[test]
+fn test_parallel_batch_skips_files_without_timestamps() {
+    let with_timestamps =
+        create_temp_log("2025-10-03T12:00:00Z ERROR one\n2025-10-03T12:00:30Z ERROR two\n");
+    let without_timestamps = create_temp_log(&"ERROR no timestamp here\n".repeat(20));
+
+    let output = run_logpile(&[
+        "ERROR",
+        "-c",
+        "--jobs",
+        "2",
+        without_timestamps.path().to_str().unwrap(),
+        with_timestamps.path().to_str().unwrap(),
+    ]);
+
+    assert!(output.contains("2025-10-03T12:00:00+00:00,2"));
+}
+
+#[test]
+fn test_timezones_align_days_to_local_midnight() {
+    // London time across the 2025-10-26 fall-back; 01:30 happens twice
+    let log = create_temp_log(
+        "2025-10-25 23:30:00 ERROR a\n\
+         2025-10-26 00:30:00 ERROR b\n\
+         2025-10-26 01:30:00 ERROR c\n\
+         2025-10-26 23:30:00 ERROR d\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    let output = run_logpile(&[
+        "ERROR",
+        path,
+        "-c",
+        "--bucket",
+        "1d",
+        "--input-tz",
+        "Europe/London",
+        "--display-tz",
+        "Europe/London",
+    ]);
+    assert!(output.contains("2025-10-25T00:00:00+01:00,1"));
+    assert!(output.contains("2025-10-26T00:00:00+01:00,3"));
+
+    // The same events bucketed by UTC day
+    let output = run_logpile(&[
+        "ERROR",
+        path,
+        "-c",
+        "--bucket",
+        "1d",
+        "--input-tz",
+        "Europe/London",
+    ]);
+    assert!(output.contains("2025-10-25T00:00:00+00:00,2"));
+    assert!(output.contains("2025-10-26T00:00:00+00:00,2"));
+}
+
+#[test]
+fn test_fill_emits_empty_buckets() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:05Z ERROR before outage\n\
+         2025-10-03T12:03:40Z ERROR after outage\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    let sparse = run_logpile(&["ERROR", path, "-c", "--bucket", "1m"]);
+    assert_eq!(sparse.lines().count(), 3);
+
+    let filled = run_logpile(&["ERROR", path, "-c", "--bucket", "1m", "--fill"]);
+    assert_eq!(
+        filled,
+        "timestamp,count\n\
+         2025-10-03T12:00:00+00:00,1\n\
+         2025-10-03T12:01:00+00:00,0\n\
+         2025-10-03T12:02:00+00:00,0\n\
+         2025-10-03T12:03:00+00:00,1\n"
+    );
+}
+
+#[test]
+fn test_since_until_window() {
+    let mut content = String::new();
+    for minute in 0..60 {
+        content.push_str(&format!("2025-10-03T12:{:02}:00Z ERROR tick\n", minute));
+    }
+    let log = create_temp_log(&content);
+    let path = log.path().to_str().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "ERROR",
+            path,
+            "-j",
+            "--bucket",
+            "10m",
+            "--since",
+            "2025-10-03T12:15:00Z",
+            "--until",
+            "2025-10-03 12:30",
+            "--verbose",
+        ])
+        .output()
+        .expect("Failed to run logpile");
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total_matches"], 15);
+    assert_eq!(json["buckets"][0]["timestamp"], "2025-10-03T12:10:00+00:00");
+    assert_eq!(json["buckets"][0]["count"], 5);
+
+    // The file is in time order, so reading stops soon after --until
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("past --until"), "stderr: {stderr}");
+}
+
+#[test]
+fn test_since_until_in_input_timezone() {
+    // London is on BST (UTC+1): 10:30Z and 11:30Z
+    let log = create_temp_log(
+        "2025-10-03 11:30:00 ERROR before
+         2025-10-03 12:30:00 ERROR after
+",
+    );
+    let stdout = run_logpile(&[
+        "ERROR",
+        log.path().to_str().unwrap(),
+        "-j",
+        "--input-tz",
+        "Europe/London",
+        "--display-tz",
+        "Asia/Tokyo",
+        "--since",
+        "2025-10-03 12:00",
+    ]);
+    // --since is London time like the log, not Tokyo time
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["total_matches"], 1);
+}
+
+#[test]
+fn test_series_mode_counts_each_pattern() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:01Z ERROR disk full\n\
+         2025-10-03T12:00:02Z WARN slow request\n\
+         2025-10-03T12:01:01Z ERROR request timeout\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    let output = run_logpile(&[
+        "ERROR",
+        path,
+        "-g",
+        "WARN",
+        "-g",
+        "timeouts=time(d )?out",
+        "--series",
+        "-c",
+    ]);
+    assert_eq!(
+        output,
+        "timestamp,ERROR,WARN,timeouts\n\
+         2025-10-03T12:00:00+00:00,1,1,0\n\
+         2025-10-03T12:01:00+00:00,1,0,1\n"
+    );
+
+    let output = run_logpile(&["ERROR", path, "-g", "WARN", "--series", "-j"]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["series"][0]["name"], "ERROR");
+    assert_eq!(json["series"][0]["total"], 2);
+    assert_eq!(json["series"][1]["name"], "WARN");
+    assert_eq!(json["series"][1]["buckets"][0]["count"], 1);
+    assert_eq!(json["total_matches"], 3);
+
+    // An escaped `=` is part of the regex, not a label
+    let log = create_temp_log("2025-10-03T12:00:01Z GET /a status=503\n");
+    let path = log.path().to_str().unwrap();
+    let output = run_logpile(&["GET", path, "-g", r"status\=5\d\d", "--series", "-c"]);
+    assert_eq!(
+        output,
+        "timestamp,GET,status\\=5\\d\\d\n\
+         2025-10-03T12:00:00+00:00,1,1\n"
+    );
+}
+
+#[test]
+fn test_series_total_counts_overlapping_lines_once() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:01Z ERROR request timeout\n\
+         2025-10-03T12:00:02Z ERROR disk full\n\
+         2025-10-03T12:01:01Z WARN request timeout\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    let output = run_logpile(&["ERROR", path, "-g", "timeout", "--series", "-j"]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["series"][0]["total"], 2);
+    assert_eq!(json["series"][1]["total"], 2);
+    assert_eq!(json["total_matches"], 3);
+
+    let output = run_logpile(&[
+        "ERROR", path, "-g", "timeout", "--series", "--value", "(\\d+)", "-j",
+    ]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["total_matches"], 3);
+}
+
+#[test]
+fn test_group_by_capture_with_top_n() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:01Z GET /a status=500\n\
+         2025-10-03T12:00:02Z GET /b status=502\n\
+         2025-10-03T12:00:03Z GET /a status=500\n\
+         2025-10-03T12:01:01Z GET /c status=503\n\
+         2025-10-03T12:01:02Z GET /c status=200\n",
+    );
+    let path = log.path().to_str().unwrap();
+    let pattern = r"status=(?P<code>5\d\d)";
+
+    let output = run_logpile(&[pattern, path, "--group-by", "code", "-c"]);
+    assert_eq!(
+        output,
+        "timestamp,500,502,503\n\
+         2025-10-03T12:00:00+00:00,2,1,0\n\
+         2025-10-03T12:01:00+00:00,0,0,1\n"
+    );
+
+    let output = run_logpile(&[pattern, path, "--group-by", "code", "--top", "1", "-j"]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["group_by"], "code");
+    assert_eq!(json["groups"]["500"], 2);
+    assert_eq!(json["groups"]["other"], 2);
+    assert_eq!(json["buckets"][0]["groups"]["other"], 1);
+    assert_eq!(json["buckets"][0]["count"], 3);
+}
+
+#[test]
+fn test_group_by_requires_named_group() {
+    let log = create_temp_log("2025-10-03T12:00:01Z status=500\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            r"status=(\d+)",
+            log.path().to_str().unwrap(),
+            "--group-by",
+            "code",
+        ])
+        .output()
+        .expect("Failed to run logpile");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Capture group 'code' not found"));
+}
+
+#[test]
+fn test_value_aggregation() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:01Z GET /a took 120ms\n\
+         2025-10-03T12:00:10Z GET /b took 80ms\n\
+         2025-10-03T12:00:30Z GET /a took 300ms\n\
+         2025-10-03T12:02:05Z GET /a took 40ms\n\
+         2025-10-03T12:02:06Z GET /c failed\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    let output = run_logpile(&[
+        "GET",
+        path,
+        "--value",
+        r"took (?P<v>\d+)ms",
+        "--agg",
+        "avg",
+        "--fill",
+        "-c",
+    ]);
+    assert_eq!(
+        output,
+        "timestamp,count,avg\n\
+         2025-10-03T12:00:00+00:00,3,166.667\n\
+         2025-10-03T12:01:00+00:00,0,\n\
+         2025-10-03T12:02:00+00:00,2,40\n"
+    );
+
+    let output = run_logpile(&["GET", path, "--value", r"(\d+)ms", "--agg", "max", "-j"]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["aggregate"], "max");
+    assert_eq!(json["value"], 300.0);
+    assert_eq!(json["buckets"][1]["count"], 2);
+    assert_eq!(json["buckets"][1]["value"], 40.0);
+
+    // Percentiles are estimated to within 1%
+    let output = run_logpile(&["GET", path, "--value", r"(\d+)ms", "--agg", "p95", "-j"]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let p95 = json["buckets"][0]["value"].as_f64().unwrap();
+    assert!((p95 - 300.0).abs() <= 3.0, "p95 = {p95}");
+
+    let output = run_logpile(&[
+        r"GET /(?P<path>\w)",
+        path,
+        "--value",
+        r"(\d+)ms",
+        "--group-by",
+        "path",
+        "-j",
+    ]);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["group_by"], "path");
+    assert_eq!(json["series"][0]["name"], "a");
+    assert_eq!(json["series"][0]["value"], 460.0);
+    assert_eq!(json["series"][2]["value"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_json_logs_fields_and_where() {
+    let log = create_temp_log(
+        "{\"request\":{\"start\":\"2025-10-03T11:58:00Z\"},\"@timestamp\":\"2025-10-03T12:00:01Z\",\"level\":\"error\",\"status\":503}\n\
+         {\"request\":{\"start\":\"2025-10-03T11:58:00Z\"},\"@timestamp\":\"2025-10-03T12:01:01Z\",\"level\":\"error\",\"status\":404}\n\
+         {\"@timestamp\":\"2025-10-03T12:01:30Z\",\"level\":\"info\",\"status\":200}\n\
+         {\"ts\":1759492925000,\"level\":\"error\",\"status\":500}\n\
+         not json 2025-10-03T12:00:00Z error\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    // @timestamp wins over the nested request.start that appears first
+    let output = run_logpile(&["error", path, "--json-logs", "-c"]);
+    assert_eq!(
+        output,
+        "timestamp,count\n\
+         2025-10-03T12:00:00+00:00,1\n\
+         2025-10-03T12:01:00+00:00,1\n\
+         2025-10-03T12:02:00+00:00,1\n"
+    );
+
+    let output = run_logpile(&[
+        "-n",
+        path,
+        "--json-logs",
+        "--where",
+        "level=error",
+        "--where",
+        "status>=500",
+        "-c",
+    ]);
+    assert_eq!(
+        output,
+        "timestamp,count\n\
+         2025-10-03T12:00:00+00:00,1\n\
+         2025-10-03T12:02:00+00:00,1\n"
+    );
+
+    let output = run_logpile(&[
+        "-n",
+        path,
+        "--json-logs",
+        "--ts-field",
+        "request.start",
+        "-c",
+    ]);
+    assert_eq!(output, "timestamp,count\n2025-10-03T11:58:00+00:00,2\n");
+}
+
+#[test]
+fn test_logfmt_fields_where_and_group_by() {
+    let log = create_temp_log(
+        "ts=2025-10-03T12:00:01Z level=warn msg=\"disk \\\"sda\\\" level=error\" dur=12ms\n\
+         ts=2025-10-03T12:00:30Z level=error msg=timeout dur=1200ms\n\
+         ts=2025-10-03T12:01:10Z level=warn msg=\"slow\" dur=300ms\n\
+         ts=2025-10-03T12:01:11Z level=info msg=ok\n\
+         garbage line 12:01:11\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    // The quoted "level=error" inside msg is not the level field
+    let output = run_logpile(&["-n", path, "--logfmt", "--where", "level=error", "-c"]);
+    assert_eq!(output, "timestamp,count\n2025-10-03T12:00:00+00:00,1\n");
+
+    let output = run_logpile(&[
+        "-n",
+        path,
+        "--logfmt",
+        "--ts-field",
+        "ts",
+        "--group-by",
+        "level",
+        "-c",
+    ]);
+    assert_eq!(
+        output,
+        "timestamp,warn,error,info\n\
+         2025-10-03T12:00:00+00:00,1,1,0\n\
+         2025-10-03T12:01:00+00:00,1,0,1\n"
+    );
+}
+
+#[test]
+fn test_multiline_records() {
+    let log = create_temp_log(
+        "2025-10-03 12:00:01 ERROR request failed\n\
+         java.lang.RuntimeException: wrapped\n\
+         \tat com.example.Foo.bar(Foo.java:42)\n\
+         Caused by: java.util.concurrent.TimeoutException\n\
+         \tat com.example.Baz.qux(Baz.java:7)\n\
+         2025-10-03 12:01:05 INFO ok\n\
+         2025-10-03 12:02:05 ERROR again\n",
+    );
+    let path = log.path().to_str().unwrap();
+
+    // The cause matches the whole event, counted at the header's timestamp
+    let output = run_logpile(&["TimeoutException", path, "--multiline", "-c"]);
+    assert_eq!(output, "timestamp,count\n2025-10-03T12:00:00+00:00,1\n");
+
+    // Each event is counted once however many lines it spans
+    let output = run_logpile(&["ERROR|Exception", path, "--record-start", r"^\d{4}-", "-c"]);
+    assert_eq!(
+        output,
+        "timestamp,count\n\
+         2025-10-03T12:00:00+00:00,1\n\
+         2025-10-03T12:02:00+00:00,1\n"
+    );
+
+    // Without record mode the continuation line has no timestamp
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args(["TimeoutException", path, "-c"])
+        .output()
+        .expect("Failed to run logpile");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_per_file_series() {
+    let dir = tempfile::tempdir().unwrap();
+    let access = dir.path().join("access.log");
+    let error = dir.path().join("error.log");
+    std::fs::write(
+        &access,
+        "2025-10-03T12:00:00Z ERROR a\n2025-10-03T12:01:00Z ERROR b\n",
+    )
+    .unwrap();
+    std::fs::write(&error, "2025-10-03T12:00:30Z ERROR c\n").unwrap();
+    let pattern = format!("{}/*.log", dir.path().display());
+
+    for jobs in ["1", "4"] {
+        let output = run_logpile(&[
+            "ERROR",
+            &pattern,
+            "--per-file",
+            "-b",
+            "1m",
+            "-c",
+            "--jobs",
+            jobs,
+        ]);
+        assert_eq!(
+            output,
+            format!(
+                "timestamp,{},{}\n\
+                 2025-10-03T12:00:00+00:00,1,1\n\
+                 2025-10-03T12:01:00+00:00,1,0\n",
+                access.display(),
+                error.display()
+            )
+        );
+    }
+}
+
+#[test]
+fn test_exclude_all_and_expr() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:01Z ERROR disk full\n\
+         2025-10-03T12:00:02Z ERROR healthcheck failed\n\
+         2025-10-03T12:00:03Z FATAL healthcheck timed out\n\
+         2025-10-03T12:00:04Z WARN disk slow\n\
+         2025-10-03T12:00:05Z ERROR disk timeout\n",
+    );
+    let path = log.path().to_str().unwrap();
+    let total = |args: &[&str]| {
+        let mut args = args.to_vec();
+        args.extend(["-j", path]);
+        let json: serde_json::Value = serde_json::from_str(&run_logpile(&args)).unwrap();
+        json["total_matches"].as_u64().unwrap()
+    };
+
+    assert_eq!(total(&["ERROR|FATAL", "--exclude", "healthcheck"]), 2);
+    assert_eq!(total(&["ERROR", "-g", "disk"]), 4);
+    assert_eq!(total(&["ERROR", "-g", "disk", "--all"]), 2);
+    assert_eq!(
+        total(&[
+            "--expr",
+            "ERROR and not healthcheck or FATAL",
+            "--exclude",
+            "disk"
+        ]),
+        1
+    );
+    assert_eq!(total(&["--expr", "(disk or FATAL) and not 'disk slow'"]), 3);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args(["--expr", "ERROR and (disk", path])
+        .output()
+        .expect("Failed to run logpile");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_verbose_reports_matches_per_pattern() {
+    let log = create_temp_log(
+        "2025-10-03T12:00:01Z ERROR disk full\n\
+         2025-10-03T12:00:02Z WARN disk slow\n\
+         2025-10-03T12:00:03Z ERROR timeout\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args(["ERROR", "-g", "disk", "-g", "FATAL", "-c", "-v"])
+        .arg(log.path())
+        .output()
+        .expect("Failed to run logpile");
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("  ERROR: 2\n"), "{}", stderr);
+    assert!(stderr.contains("  disk: 2\n"), "{}", stderr);
+    assert!(stderr.contains("  FATAL: 0\n"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(",3\n"));
+}
+
+#[test]
+fn test_detected_timestamp_formats_reported() {
+    let iso = create_temp_log(
+        "2025-10-03T12:00:01Z ERROR one\n\
+         2025-10-03T12:00:02Z ERROR two\n",
+    );
+    let apache = create_temp_log(
+        "[03/Oct/2025:12:00:03 +0000] ERROR three\n\
+         [03/Oct/2025:12:00:04 +0000] ERROR four\n",
+    );
+    let iso_path = iso.path().to_str().unwrap();
+    let apache_path = apache.path().to_str().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args(["ERROR", "-j", "-v", iso_path, apache_path])
+        .output()
+        .expect("Failed to run logpile");
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total_matches"], 4);
+    assert_eq!(json["timestamp_formats"][iso_path], "%Y-%m-%dT%H:%M:%S%.fZ");
+    assert_eq!(
+        json["timestamp_formats"][apache_path],
+        "[%d/%b/%Y:%H:%M:%S %z]"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Timestamp format: [%d/%b/%Y:%H:%M:%S %z]"));
+}
+
+#[test]
+fn test_epoch_timestamps_in_field() {
+    // 12:00:00.123, 12:00:59.900 and 12:01:30.000 on 2025-10-03, in ms,
+    // after a pid that also looks like an epoch in seconds
+    let file = create_temp_log(
+        "pid=1712345678 ts=1759492800123 ERROR one\n\
+         pid=1712345678 ts=1759492859900 ERROR two\n\
+         pid=1712345678 ts=1759492890000 ERROR three\n",
+    );
+    let path = file.path().to_str().unwrap();
+
+    let stdout = run_logpile(&["ERROR", "-j", "-b", "1m", "--ts-field", "ts", path]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["buckets"][0]["timestamp"], "2025-10-03T12:00:00+00:00");
+    assert_eq!(json["buckets"][0]["count"], 2);
+    assert_eq!(json["buckets"][1]["timestamp"], "2025-10-03T12:01:00+00:00");
+    assert_eq!(json["buckets"][1]["count"], 1);
+    assert_eq!(json["timestamp_formats"][path], "epoch_ms");
+
+    // An explicit unit reads the first number in the field
+    let stdout = run_logpile(&[
+        "ERROR",
+        "-j",
+        "-b",
+        "1m",
+        "--ts-field",
+        "ts",
+        "-t",
+        "epoch_ms",
+        path,
+    ]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["buckets"][0]["count"], 2);
+}
+
+#[test]
+fn test_syslog_year_rollover() {
+    let file = create_temp_log(
+        "Dec 31 23:58:00 host app: ERROR one\n\
+         Dec 31 23:59:00 host app: ERROR two\n\
+         Jan  1 00:01:00 host app: ERROR three\n",
+    );
+    // Last written on January 2nd, 2026
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_767_312_000);
+    file.as_file().set_modified(modified).unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let stdout = run_logpile(&["ERROR", "-j", "-b", "1d", path]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["buckets"][0]["timestamp"], "2025-12-31T00:00:00+00:00");
+    assert_eq!(json["buckets"][0]["count"], 2);
+    assert_eq!(json["buckets"][1]["timestamp"], "2026-01-01T00:00:00+00:00");
+    assert_eq!(json["buckets"][1]["count"], 1);
+
+    // --reference-date takes precedence over the modification time
+    let stdout = run_logpile(&[
+        "ERROR",
+        "-j",
+        "-b",
+        "1d",
+        "--reference-date",
+        "2023-01-01",
+        path,
+    ]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["buckets"][0]["timestamp"], "2022-12-31T00:00:00+00:00");
+    assert_eq!(json["buckets"][1]["timestamp"], "2023-01-01T00:00:00+00:00");
+}
+
+#[test]
+fn test_chunked_file_matches_sequential() {
+    let mut content = String::new();
+    for i in 0..400 {
+        let level = if i % 3 == 0 { "ERROR" } else { "INFO" };
+        content.push_str(&format!(
+            "2025-10-03T12:{:02}:{:02}Z {} request {}\n",
+            i / 60,
+            i % 60,
+            level,
+            i
+        ));
+    }
+    let file = create_temp_log(&content);
+    let path = file.path().to_str().unwrap();
+
+    let sequential = run_logpile(&["ERROR", "-j", "-b", "1m", "--jobs", "1", path]);
+    // About 16 KiB in 1 KiB chunks: four chunks, one per job
+    let (chunked, _) = run_logpile_chunked(&["ERROR", "-j", "-b", "1m", "--jobs", "4", path], 1024);
+    assert_eq!(sequential, chunked);
+    let json: serde_json::Value = serde_json::from_str(&chunked).unwrap();
+    assert_eq!(json["total_matches"], 134);
+}
+
+#[test]
+fn test_chunked_file_timestamp_probe_matches_sequential() {
+    // The first matching lines have no timestamp, across several chunks
+    let timestamped = "2025-10-03T12:00:00Z ERROR late\n".repeat(20);
+    let skipped = create_temp_log(&format!(
+        "{}{}",
+        "ERROR no timestamp here\n".repeat(15),
+        timestamped
+    ));
+    // Fewer than the probe limit: the file is still counted
+    let counted = create_temp_log(&format!(
+        "{}{}",
+        "ERROR no timestamp here\n".repeat(5),
+        timestamped
+    ));
+    let good = create_temp_log("2025-10-03T12:00:30Z ERROR fine\n");
+
+    for file in [&skipped, &counted] {
+        let paths = [file.path().to_str().unwrap(), good.path().to_str().unwrap()];
+        let run = |jobs: &str| {
+            run_logpile_chunked(
+                &["ERROR", "-j", "-v", "--jobs", jobs, paths[0], paths[1]],
+                64,
+            )
+        };
+        let (sequential, sequential_stderr) = run("1");
+        let (chunked, chunked_stderr) = run("8");
+        assert_eq!(sequential, chunked);
+        assert_eq!(sequential_stderr, chunked_stderr);
+    }
+
+    let (stdout, stderr) = run_logpile_chunked(
+        &[
+            "ERROR",
+            "-j",
+            "-v",
+            "--jobs",
+            "8",
+            skipped.path().to_str().unwrap(),
+            good.path().to_str().unwrap(),
+        ],
+        64,
+    );
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["total_matches"], 1);
+    assert!(stderr.contains("No valid timestamps found in first 11 matching lines"));
+
+    let (stdout, _) = run_logpile_chunked(
+        &[
+            "ERROR",
+            "-j",
+            "--jobs",
+            "8",
+            counted.path().to_str().unwrap(),
+        ],
+        64,
+    );
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["total_matches"], 20);
+}
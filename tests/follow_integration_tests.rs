@@ -438,3 +438,406 @@ fn test_follow_mode_file_creation() {
         "Should either process the file content or show an appropriate error message"
     );
 }
+
+/// Test that a line written in pieces is counted once, when it is complete
+#[test]
+fn test_follow_mode_partial_lines_counted_once() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file
+        .write_all(b"2025-10-03T12:00:00Z INFO First\n2025-10-03T12:00:10Z IN")
+        .expect("Failed to write initial content");
+    temp_file.flush().expect("Failed to flush");
+
+    let file_path = temp_file.path().to_str().unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "INFO",
+            file_path,
+            "--follow",
+            "--csv",
+            "--no-headers",
+            "--bucket",
+            "1m",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    thread::sleep(Duration::from_millis(1000));
+
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(file_path)
+        .expect("Failed to open file for appending");
+    file.write_all(b"FO Second\n2025-10-03T12:00:20Z INFO Third\n")
+        .expect("Failed to append");
+    file.flush().expect("Failed to flush");
+
+    thread::sleep(Duration::from_millis(2000));
+
+    child.kill().expect("Failed to kill process");
+    let output = child.wait_with_output().expect("Failed to get output");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // The latest refresh counts all three lines, with no double counting
+    let last = stdout
+        .lines()
+        .rfind(|line| line.contains("2025-10-03T12:00:00"))
+        .expect("Should print the 12:00 bucket");
+    assert!(last.ends_with(",3"), "Expected a count of 3, got: {}", last);
+    assert!(
+        !stdout.lines().any(|line| line.ends_with(",4")),
+        "Lines should not be counted twice: {}",
+        stdout
+    );
+}
+
+/// Test that follow mode keeps counting across a logrotate-style rename
+#[cfg(unix)]
+#[test]
+fn test_follow_mode_log_rotation() {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let file_path = temp_dir.path().join("app.log");
+    let rotated_path = temp_dir.path().join("app.log.1");
+    std::fs::write(&file_path, "2025-10-03T12:00:00Z INFO Before rotation\n")
+        .expect("Failed to write initial content");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "INFO",
+            file_path.to_str().unwrap(),
+            "--follow",
+            "--csv",
+            "--no-headers",
+            "--bucket",
+            "1m",
+            "--verbose",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    thread::sleep(Duration::from_millis(1000));
+
+    // A late write to the old file, then a fresh file under the same name
+    let mut old_file = OpenOptions::new()
+        .append(true)
+        .open(&file_path)
+        .expect("Failed to open file for appending");
+    std::fs::rename(&file_path, &rotated_path).expect("Failed to rotate");
+    old_file
+        .write_all(b"2025-10-03T12:00:10Z INFO Late write to rotated file\n")
+        .expect("Failed to append");
+    std::fs::write(
+        &file_path,
+        "2025-10-03T12:00:20Z INFO After rotation\n2025-10-03T12:00:30Z INFO Again\n",
+    )
+    .expect("Failed to create new file");
+
+    thread::sleep(Duration::from_millis(2000));
+
+    child.kill().expect("Failed to kill process");
+    let output = child.wait_with_output().expect("Failed to get output");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let last = stdout
+        .lines()
+        .rfind(|line| line.contains("2025-10-03T12:00:00"))
+        .expect("Should print the 12:00 bucket");
+    assert!(last.ends_with(",4"), "Expected a count of 4, got: {}", last);
+    assert!(
+        stderr.contains("was rotated"),
+        "Should report the rotation: {}",
+        stderr
+    );
+}
+
+/// Test that a copytruncate-style truncation is not counted twice
+#[test]
+fn test_follow_mode_truncation() {
+    let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    let file_path = temp_file.path().to_str().unwrap();
+    std::fs::write(
+        file_path,
+        "2025-10-03T12:00:00Z INFO One\n2025-10-03T12:00:10Z INFO Two\n",
+    )
+    .expect("Failed to write initial content");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "INFO",
+            file_path,
+            "--follow",
+            "--csv",
+            "--no-headers",
+            "--bucket",
+            "1m",
+            "--verbose",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    thread::sleep(Duration::from_millis(1000));
+
+    std::fs::write(file_path, "2025-10-03T12:00:20Z INFO Three\n").expect("Failed to truncate");
+
+    thread::sleep(Duration::from_millis(2000));
+
+    child.kill().expect("Failed to kill process");
+    let output = child.wait_with_output().expect("Failed to get output");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let last = stdout
+        .lines()
+        .rfind(|line| line.contains("2025-10-03T12:00:00"))
+        .expect("Should print the 12:00 bucket");
+    assert!(last.ends_with(",3"), "Expected a count of 3, got: {}", last);
+    assert!(
+        stderr.contains("was truncated"),
+        "Should report the truncation: {}",
+        stderr
+    );
+}
+
+/// Test following several files, including one created later that matches a glob
+#[test]
+fn test_follow_mode_multiple_files_and_globs() {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let access_path = temp_dir.path().join("access.log");
+    let error_path = temp_dir.path().join("error.log");
+    std::fs::write(&access_path, "2025-10-03T12:00:00Z ERROR upstream\n")
+        .expect("Failed to write access log");
+    std::fs::write(&error_path, "2025-10-03T12:00:05Z ERROR crash\n")
+        .expect("Failed to write error log");
+    let pattern = format!("{}/*.log", temp_dir.path().display());
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "ERROR",
+            &pattern,
+            "--follow",
+            "--csv",
+            "--per-file",
+            "--bucket",
+            "1m",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    thread::sleep(Duration::from_millis(1000));
+
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(&error_path)
+        .expect("Failed to open file for appending");
+    file.write_all(b"2025-10-03T12:00:10Z ERROR crash again\n")
+        .expect("Failed to append");
+    let new_path = temp_dir.path().join("worker.log");
+    std::fs::write(&new_path, "2025-10-03T12:00:20Z ERROR worker\n")
+        .expect("Failed to create new file");
+
+    thread::sleep(Duration::from_millis(2500));
+
+    child.kill().expect("Failed to kill process");
+    let output = child.wait_with_output().expect("Failed to get output");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let header = stdout
+        .lines()
+        .rfind(|line| line.contains("timestamp,"))
+        .expect("Should print a header");
+    assert!(
+        header.ends_with(&format!(
+            "timestamp,{},{},{}",
+            access_path.display(),
+            error_path.display(),
+            new_path.display()
+        )),
+        "Unexpected header: {}",
+        header
+    );
+    let last = stdout
+        .lines()
+        .rfind(|line| line.starts_with("2025-10-03T12:00:00"))
+        .expect("Should print the 12:00 bucket");
+    assert_eq!(last, "2025-10-03T12:00:00+00:00,1,2,1");
+    assert!(
+        stderr.contains("Following new file"),
+        "Should report the new file: {}",
+        stderr
+    );
+}
+
+/// Test that --window drops events and buckets older than the window
+#[test]
+fn test_follow_mode_sliding_window() {
+    let now = chrono::Utc::now();
+    let line = |minutes_ago: i64, message: &str| {
+        format!(
+            "{} ERROR {}\n",
+            (now - chrono::Duration::minutes(minutes_ago)).format("%Y-%m-%dT%H:%M:%SZ"),
+            message
+        )
+    };
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file
+        .write_all((line(30, "stale") + &line(2, "recent") + &line(0, "now")).as_bytes())
+        .expect("Failed to write initial content");
+    temp_file.flush().expect("Failed to flush");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "ERROR",
+            temp_file.path().to_str().unwrap(),
+            "--follow",
+            "--json",
+            "--window",
+            "10m",
+            "--bucket",
+            "1m",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    thread::sleep(Duration::from_millis(1000));
+
+    child.kill().expect("Failed to kill process");
+    let output = child.wait_with_output().expect("Failed to get output");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let latest = stdout
+        .rsplit("\x1B[2J\x1B[1;1H")
+        .next()
+        .expect("Should print results");
+    let json: serde_json::Value = serde_json::from_str(latest).expect("Should print valid JSON");
+
+    assert_eq!(json["total_matches"], 2);
+    // The time range is the window itself, ending now
+    let start = json["time_range"]["start"].as_str().unwrap();
+    let end = json["time_range"]["end"].as_str().unwrap();
+    let start = chrono::DateTime::parse_from_rfc3339(start).unwrap();
+    let end = chrono::DateTime::parse_from_rfc3339(end).unwrap();
+    assert_eq!(end - start, chrono::Duration::minutes(10));
+    assert!(end >= now);
+}
+
+#[test]
+fn test_follow_mode_stream_csv() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    writeln!(temp_file, "2025-01-01 10:00:05 ERROR first").unwrap();
+    writeln!(temp_file, "2025-01-01 10:20:40 ERROR second").unwrap();
+    writeln!(temp_file, "2025-01-01 11:01:10 ERROR third").unwrap();
+    temp_file.flush().expect("Failed to flush");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            "ERROR",
+            temp_file.path().to_str().unwrap(),
+            "--follow",
+            "--stream",
+            "--csv",
+            "--bucket",
+            "1h",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    thread::sleep(Duration::from_millis(800));
+
+    // A late event for a bucket already emitted, and one in the bucket that
+    // is still open
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(temp_file.path())
+        .expect("Failed to open file for appending");
+    writeln!(file, "2025-01-01 10:30:00 ERROR late").unwrap();
+    writeln!(
+        file,
+        "{} ERROR current",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")
+    )
+    .unwrap();
+    file.flush().expect("Failed to flush");
+
+    thread::sleep(Duration::from_millis(2000));
+
+    child.kill().expect("Failed to kill process");
+    let output = child.wait_with_output().expect("Failed to get output");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        !stdout.contains('\x1B'),
+        "Stream output should have no escape codes"
+    );
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "timestamp,count",
+            "2025-01-01T10:00:00+00:00,2",
+            "2025-01-01T11:00:00+00:00,1",
+        ]
+    );
+}
+
+#[test]
+fn test_follow_mode_stream_ndjson_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_logpile"))
+        .args([
+            r"status=(?P<status>\d+)",
+            "--follow",
+            "--stream",
+            "--json",
+            "--provisional",
+            "--group-by",
+            "status",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start logpile");
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(
+            b"2025-01-01 10:00:05 status=500\n\
+              2025-01-01 10:00:10 status=404\n\
+              2025-01-01 10:00:20 status=500\n\
+              2025-01-01 10:01:30 status=500\n",
+        )
+        .unwrap();
+    // Closing stdin ends the input, so every bucket is complete
+    drop(stdin);
+
+    let output = child.wait_with_output().expect("Failed to get output");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Each line should be a JSON object"))
+        .collect();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["timestamp"], "2025-01-01T10:00:00+00:00");
+    assert_eq!(records[0]["count"], 3);
+    assert_eq!(records[0]["groups"]["500"], 2);
+    assert_eq!(records[0]["groups"]["404"], 1);
+    assert_eq!(records[0]["provisional"], false);
+    assert_eq!(records[1]["timestamp"], "2025-01-01T10:01:00+00:00");
+    assert_eq!(records[1]["count"], 1);
+    assert_eq!(records[1]["provisional"], false);
+}
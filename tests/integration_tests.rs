@@ -403,7 +403,7 @@ fn test_follow_mode_with_custom_bucket_size() {
 
     // Should show bucket size information
     assert!(
-        stdout.contains("Bucket size: 60 seconds"),
+        stdout.contains("Bucket size: 1m"),
         "Should show custom bucket size"
     );
 }
@@ -442,7 +442,10 @@ fn test_follow_mode_with_auto_bucket() {
 
     // Should show auto-calculated bucket size
     assert!(stdout.contains("Bucket size:"), "Should show bucket size");
-    assert!(stdout.contains("seconds"), "Should show seconds unit");
+    assert!(
+        !stdout.contains("seconds"),
+        "Should show human units rather than raw seconds"
+    );
 }
 
 #[test]